@@ -6,7 +6,13 @@ import { Socket } from 'net';
 import { BufferWriter } from 'protobufjs';
 import { ClientLogger, Logger } from './Logger';
 import { TextDecoder } from 'text-encoding';
-import { ServerMessage, GameState, PlayerAction } from './PwTypes';
+import {
+    ServerMessage,
+    GameState,
+    PlayerAction,
+    GameInfo,
+    DebugInfo,
+} from './PwTypes';
 import { RequestResolver } from './RequestResolver';
 import { SimpleEventDispatcher, ISimpleEvent } from 'ste-simple-events';
 import { SignalDispatcher, ISignal } from 'ste-signals';
@@ -119,6 +125,9 @@ export class Client {
             case 'final_state': {
                 return this.handleFinalState(serverMessage.content);
             }
+            case 'game_info': {
+                return this.handleGameInfo(serverMessage.content);
+            }
         }
     }
 
@@ -128,20 +137,51 @@ export class Client {
         return new Promise((resolve, reject) => {
             const request = JSON.stringify(state);
             this.botRunner.request(request, (response) => {
-                this.logger.log({
-                    "type": "command",
-                    "content": response,
-                });
+                this.logResponse(response);
                 const buf = Buffer.from(response, 'utf-8');
                 resolve(buf);
             });
         });
     }
 
+    // debug info gets a record of its own, so that it is logged only once
+    private logResponse(response: string) {
+        let content = response;
+        let debug: DebugInfo | undefined;
+        try {
+            const action = JSON.parse(response);
+            if (action && action.debug) {
+                debug = action.debug;
+                delete action.debug;
+                content = JSON.stringify(action);
+            }
+        } catch (e) {
+            // the server reports responses it can not parse
+        }
+
+        this.logger.log({
+            "type": "command",
+            "content": content,
+        });
+        if (debug) {
+            this.logger.log({
+                "type": "debug",
+                "debug": debug,
+            });
+        }
+    }
+
     private handleFinalState(state: GameState) {
         this.logState(state);
     }
 
+    private handleGameInfo(info: GameInfo) {
+        this.logger.log({
+            "type": 'game_info',
+            "info": info,
+        });
+    }
+
     private logState(state: GameState) {
         this.turnNum += 1;
 
@@ -17,6 +17,14 @@ export interface MatchParams {
     logFile: string;
 }
 
+export interface PlayerInfo {
+    name?: string;
+    botVersion?: string;
+    ownerId?: string;
+    // player number to play as, counting from 1
+    seat?: number;
+}
+
 export class MatchRunner {
     private connection: Connection;
     private serverRunner: ServerRunner;
@@ -29,6 +37,11 @@ export class MatchRunner {
 
     private _onPlayerConnected = new SimpleEventDispatcher<number>();
     private _onPlayerDisconnected = new SimpleEventDispatcher<number>();
+    private _onPlayersForfeited = new SimpleEventDispatcher<number[]>();
+    private _onMatchCancelled = new SimpleEventDispatcher<string>();
+    private _onMatchAborted = new SimpleEventDispatcher<string>();
+    private _onPlayerDisqualified = new SimpleEventDispatcher<Disqualification>();
+    private _onMatchResult = new SimpleEventDispatcher<MatchResult>();
 
     constructor(serverPath: string, params: MatchParams) {
         this.serverRunner = new ServerRunner(serverPath, params);
@@ -61,6 +74,29 @@ export class MatchRunner {
                     this._onPlayerDisconnected.dispatch(player_id);
                     break;
                 }
+                case 'players_forfeited': {
+                    const { player_ids } = message.content;
+                    this._onPlayersForfeited.dispatch(player_ids);
+                    break;
+                }
+                case 'match_cancelled': {
+                    const { reason } = message.content;
+                    this._onMatchCancelled.dispatch(reason);
+                    break;
+                }
+                case 'match_aborted': {
+                    const { reason } = message.content;
+                    this._onMatchAborted.dispatch(reason);
+                    break;
+                }
+                case 'player_disqualified': {
+                    this._onPlayerDisqualified.dispatch(message.content);
+                    break;
+                }
+                case 'match_result': {
+                    this._onMatchResult.dispatch(message.content);
+                    break;
+                }
                 case 'game_state': {
                     this.logger.log({
                         type: "game_state",
@@ -85,23 +121,75 @@ export class MatchRunner {
         });
     }
 
-    public addPlayer(token: Uint8Array): Promise<number> {
-        let addPlayer = LobbyMessage.AddPlayerRequest.create({ token });
+    public addPlayer(token: Uint8Array, info: PlayerInfo = {}): Promise<number> {
+        let addPlayer = LobbyMessage.AddPlayerRequest.create({
+            token,
+            name: info.name,
+            botVersion: info.botVersion,
+            ownerId: info.ownerId,
+            seat: info.seat,
+        });
         return this.lobbyRequest({ addPlayer }).then((data) => {
             const response = LobbyMessage.AddPlayerResponse.decode(data);
+            if (response.error) {
+                throw new Error(response.error.message);
+            }
             return Number(response.clientId);
         });
     }
 
     public removePlayer(clientId: number): Promise<void> {
         let removePlayer = new LobbyMessage.RemovePlayerRequest({ clientId });
-        return this.lobbyRequest({ removePlayer }).then((data) => { });
+        return this.lobbyRequest({ removePlayer }).then((data) => {
+            const response = LobbyMessage.RemovePlayerResponse.decode(data);
+            if (response.error) {
+                throw new Error(response.error.message);
+            }
+        });
     }
 
-    public startGame(config: object): Promise<void> {
+    // resolves to the warnings about settings that can never take effect
+    public startGame(config: object): Promise<string[]> {
         let payload = Buffer.from(JSON.stringify(config), 'utf-8');
         let startGame = new LobbyMessage.StartGameRequest({ payload });
-        return this.lobbyRequest({ startGame }).then((data) => { });
+        return this.lobbyRequest({ startGame }).then((data) => {
+            const response = LobbyMessage.StartGameResponse.decode(data);
+            if (response.error) {
+                throw new Error(response.error.message);
+            }
+            return response.success ? response.success.warnings : [];
+        });
+    }
+
+    // pause the match once the current turn is over
+    public pause(): Promise<void> {
+        let pause = new LobbyMessage.PauseRequest();
+        return this.controlRequest({ pause });
+    }
+
+    public resume(): Promise<void> {
+        let resume = new LobbyMessage.ResumeRequest();
+        return this.controlRequest({ resume });
+    }
+
+    // play a single turn of a paused match
+    public step(): Promise<void> {
+        let step = new LobbyMessage.StepRequest();
+        return this.controlRequest({ step });
+    }
+
+    public abort(reason: string): Promise<void> {
+        let abort = new LobbyMessage.AbortRequest({ reason });
+        return this.controlRequest({ abort });
+    }
+
+    private controlRequest(params: proto.ILobbyMessage): Promise<void> {
+        return this.lobbyRequest(params).then((data) => {
+            const response = LobbyMessage.ControlResponse.decode(data);
+            if (response.error) {
+                throw new Error(response.error.message);
+            }
+        });
     }
 
     private lobbyRequest(params: proto.ILobbyMessage): Promise<Uint8Array> {
@@ -143,6 +231,26 @@ export class MatchRunner {
         return this._onPlayerDisconnected.asEvent();
     }
 
+    public get onPlayersForfeited() {
+        return this._onPlayersForfeited.asEvent();
+    }
+
+    public get onMatchCancelled() {
+        return this._onMatchCancelled.asEvent();
+    }
+
+    public get onMatchAborted() {
+        return this._onMatchAborted.asEvent();
+    }
+
+    public get onPlayerDisqualified() {
+        return this._onPlayerDisqualified.asEvent();
+    }
+
+    public get onMatchResult() {
+        return this._onMatchResult.asEvent();
+    }
+
     public get onConnect() {
         return this.connection.onConnect;
     }
@@ -151,6 +259,11 @@ export class MatchRunner {
 type ServerMessage
     = PlayerConnectedMessage
     | PlayerDisconnectedMessage
+    | PlayersForfeitedMessage
+    | MatchCancelledMessage
+    | MatchAbortedMessage
+    | PlayerDisqualifiedMessage
+    | MatchResultMessage
     | GameStateMessage;
 
 
@@ -168,7 +281,49 @@ interface PlayerDisconnectedMessage {
     }
 }
 
+interface PlayersForfeitedMessage {
+    type: "players_forfeited";
+    content: {
+        player_ids: number[];
+    }
+}
+
+interface MatchCancelledMessage {
+    type: "match_cancelled";
+    content: {
+        reason: string;
+    }
+}
+
+interface MatchAbortedMessage {
+    type: "match_aborted";
+    content: {
+        reason: string;
+    }
+}
+
+export interface Disqualification {
+    player_id: number;
+    reason: string;
+}
+
+// how a round ended; players are identified by client id
+export interface MatchResult {
+    survivors: number[];
+    disqualified: Disqualification[];
+}
+
+interface PlayerDisqualifiedMessage {
+    type: "player_disqualified";
+    content: Disqualification;
+}
+
+interface MatchResultMessage {
+    type: "match_result";
+    content: MatchResult;
+}
+
 interface GameStateMessage {
     type: "game_state";
     content: GameState;
-}
\ No newline at end of file
+}
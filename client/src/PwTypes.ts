@@ -1,6 +1,18 @@
 export interface GameState {
+    "turn_num": number,
+    "max_turns": number,
+    "num_players": number,
+    // only in states sent to players
+    "player_id"?: number,
+    "time_budget"?: number,
     "planets": Planet[],
     "expeditions": Expedition[],
+    "travel_times"?: TravelTimes[],
+}
+
+export interface TravelTimes {
+    "min_ships": number;
+    "turns": { [origin: string]: { [destination: string]: number } };
 }
 
 export interface Planet {
@@ -9,12 +21,15 @@ export interface Planet {
     "y": number;
     "owner": number;
     "name": string;
+    "growth_rate"?: number;
+    "radius"?: number;
 }
 
 export interface Expedition {
     "id": number;
     "origin": string;
-    "destination": string;
+    // null for enemy expeditions when the match hides destinations
+    "destination": string | null;
     "owner": number;
     "ship_count": number;
     "turns_remaining": number;
@@ -26,6 +41,19 @@ export interface Command {
     "ship_count": number;
 }
 
+/**
+ * Diagnostics a bot can send along with its moves. Only its owner sees them.
+ */
+export interface DebugInfo {
+    "messages"?: string[];
+    "annotations"?: Annotation[];
+}
+
+export interface Annotation {
+    "planet": string;
+    "label": string;
+}
+
 /**
  * A PlayerAction describes the action a player took in his turn
  */
@@ -58,7 +86,10 @@ export type DispatchError
     | 'OriginNotOwned'
     | 'ZeroShipMove'
     | 'OriginDoesNotExist'
-    | 'DestinationDoesNotExist';
+    | 'DestinationDoesNotExist'
+    | 'SelfTarget'
+    | 'DuplicateCommand'
+    | 'Overdrawn';
 
 
 /**
@@ -68,7 +99,8 @@ export type DispatchError
 export type ServerMessage
     = GameStateMessage
     | PlayerActionMessage
-    | FinalStateMessage;
+    | FinalStateMessage
+    | GameInfoMessage;
 
 
 export type GameStateMessage = {
@@ -86,6 +118,24 @@ export type PlayerActionMessage = {
     content: PlayerAction;
 }
 
+export type GameInfoMessage = {
+    type: 'game_info';
+    content: GameInfo;
+}
+
+/**
+ * The players in a match, in the order of their player numbers.
+ */
+export interface GameInfo {
+    players: PlayerInfo[];
+}
+
+export interface PlayerInfo {
+    name: string;
+    bot_version: string;
+    owner_id: string;
+}
+
 /**
  * Types for the log
  */
@@ -108,7 +158,9 @@ export interface PlayerLogEntry {
 export type LogRecord
     = StepRecord
     | CommandRecord
-    | PlayerActionRecord;
+    | PlayerActionRecord
+    | DebugRecord
+    | GameInfoRecord;
 
     
 export interface StepRecord {
@@ -125,4 +177,14 @@ export interface CommandRecord {
 export interface PlayerActionRecord {
     "type": "player_action";
     "action": PlayerAction;
+}
+
+export interface DebugRecord {
+    "type": "debug";
+    "debug": DebugInfo;
+}
+
+export interface GameInfoRecord {
+    "type": "game_info";
+    "info": GameInfo;
 }
\ No newline at end of file
@@ -97,10 +97,15 @@ export interface ServerConfigJSON {
     ctrl_token: string;
     address: string;
     log_file: string;
+    map_pool?: string;
 }
 
 export interface GameConfigJSON {
-    map_file: string;
+    // exactly one of the map fields should be present
+    map_file?: string;
+    map?: { planets: object[] };
+    map_name?: string;
+    generate_map?: object;
     max_turns: number;
 }
 
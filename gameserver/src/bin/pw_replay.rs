@@ -0,0 +1,208 @@
+//! Re-simulates a planet wars match from its log.
+//!
+//! The match is replayed up to the requested turn. When a bot command is
+//! given, that bot takes over the given player from there on, while the other
+//! players keep replaying their logged responses.
+//!
+//! The log holds the debug output of every player; `--debug` prints only
+//! that of one player, so it can be handed to the bot's owner.
+
+extern crate mozaic_bot_driver;
+extern crate mozaic_client;
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+extern crate slog_json;
+
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use slog::Drain;
+
+use mozaic_bot_driver::planetwars::{MatchLog, Replay, PlayerId};
+use mozaic_client::bot_process::BotProcess;
+
+const USAGE: &str = "\
+usage: pw_replay [--output <file>] [--deadline <ms>] [--round <n>] \
+[--debug <player>] <log file> <turn> [<player> <command> [args...]]
+
+Without a bot command, prints the game state at the given turn.
+Players are numbered starting from 1.
+
+  --output <file>  write the replayed match to this log file
+  --deadline <ms>  time the local bot gets per turn (default 1000)
+  --round <n>      the round to replay, for matches with swapped seats
+                   (default 0)
+  --debug <player> print the debug output the player sent up to the
+                   given turn, instead of replaying";
+
+struct ReplayConfig {
+    output: Option<String>,
+    deadline: Duration,
+    round: usize,
+    debug: Option<PlayerId>,
+    log_file: String,
+    turn: u64,
+    bot: Option<(PlayerId, String, Vec<String>)>,
+}
+
+fn main() {
+    let config = match parse_args(env::args().skip(1).collect()) {
+        Ok(config) => config,
+        Err(msg) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(err) = run(config) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: Vec<String>) -> Result<ReplayConfig, String> {
+    let mut output = None;
+    let mut deadline = 1000;
+    let mut round = 0;
+    let mut debug = None;
+
+    while !args.is_empty() && args[0].starts_with("--") {
+        let flag = args.remove(0);
+        if args.is_empty() {
+            return Err(format!("missing value for {}", flag));
+        }
+        let value = args.remove(0);
+        match flag.as_str() {
+            "--output" => output = Some(value),
+            "--deadline" => {
+                deadline = value.parse()
+                    .map_err(|_| format!("invalid deadline: {}", value))?;
+            }
+            "--round" => {
+                round = value.parse()
+                    .map_err(|_| format!("invalid round: {}", value))?;
+            }
+            "--debug" => debug = Some(parse_player(&value)?),
+            _ => return Err(format!("unknown flag: {}", flag)),
+        }
+    }
+
+    if args.len() < 2 {
+        return Err("expected a log file and a turn number".to_string());
+    }
+    let log_file = args.remove(0);
+    let turn = args.remove(0).parse()
+        .map_err(|_| "invalid turn number".to_string())?;
+
+    let bot = match args.len() {
+        0 => None,
+        1 => return Err("expected a bot command".to_string()),
+        _ => {
+            let player_id = parse_player(&args.remove(0))?;
+            let command = args.remove(0);
+            Some((player_id, command, args))
+        }
+    };
+
+    Ok(ReplayConfig {
+        output,
+        deadline: Duration::from_millis(deadline),
+        round,
+        debug,
+        log_file,
+        turn,
+        bot,
+    })
+}
+
+fn parse_player(arg: &str) -> Result<PlayerId, String> {
+    let player_num: usize = arg.parse()
+        .map_err(|_| "invalid player number".to_string())?;
+    if player_num == 0 {
+        return Err("player numbers start from 1".to_string());
+    }
+    Ok(PlayerId::new(player_num - 1))
+}
+
+fn run(config: ReplayConfig) -> Result<(), Box<std::error::Error>> {
+    let reader = BufReader::new(File::open(&config.log_file)?);
+    let log = MatchLog::read_round(reader, config.round)?;
+
+    let logger = match config.output {
+        Some(ref path) => {
+            let file = File::create(path)?;
+            slog::Logger::root(
+                Mutex::new(slog_json::Json::default(file)).map(slog::Fuse),
+                o!()
+            )
+        }
+        None => slog::Logger::root(slog::Discard, o!()),
+    };
+
+    let players = config.debug.iter()
+        .chain(config.bot.iter().map(|(player_id, _, _)| player_id));
+    for player_id in players {
+        if player_id.as_usize() >= log.num_players {
+            return Err(format!(
+                "player {} does not exist in this match",
+                player_id.as_usize() + 1,
+            ).into());
+        }
+    }
+
+    if let Some(player_id) = config.debug {
+        for (turn_num, info) in log.player_debug(player_id) {
+            if turn_num <= config.turn {
+                println!("turn {}: {}", turn_num, serde_json::to_string(info)?);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut replay = Replay::new(log, logger);
+    for turn in replay.seek(config.turn) {
+        eprintln!("warning: replay diverged from log in turn {}", turn);
+    }
+    if replay.turn_num() < config.turn {
+        eprintln!("warning: match ended in turn {}", replay.turn_num());
+    }
+
+    let (player_id, command, args) = match config.bot {
+        None => {
+            let state = replay.serialized_state();
+            println!("{}", serde_json::to_string_pretty(&state)?);
+            return Ok(());
+        }
+        Some(bot) => bot,
+    };
+
+    let mut bot = BotProcess::spawn::<File>(&command, &args, None)?;
+    let deadline = config.deadline;
+    while !replay.is_finished() {
+        let actions = replay.play_substituted_turn(player_id, |state| {
+            match bot.request(&state, deadline) {
+                Ok(response) => response,
+                Err(err) => {
+                    eprintln!("bot failed: {}", err);
+                    None
+                }
+            }
+        });
+
+        for (id, action) in actions {
+            if id == player_id {
+                println!("turn {}: {}",
+                    replay.turn_num(),
+                    serde_json::to_string(&action)?,
+                );
+            }
+        }
+    }
+    println!("final state: {}", serde_json::to_string(&replay.serialized_state())?);
+    Ok(())
+}
@@ -0,0 +1,44 @@
+#![allow(dead_code)]
+mod network;
+pub mod oneshot_server;
+pub mod planetwars;
+mod protobuf_codec;
+mod utils;
+
+pub mod protocol {
+    include!(concat!(env!("OUT_DIR"), "/mozaic.protocol.rs"));
+}
+
+
+extern crate bytes;
+extern crate hex;
+extern crate map_generator;
+extern crate map_model;
+extern crate planetwars_rules;
+extern crate rand;
+
+extern crate tokio_core;
+extern crate tokio_io;
+extern crate tokio_process;
+extern crate tokio;
+extern crate tokio_timer;
+#[macro_use]
+extern crate futures;
+
+extern crate serde;
+extern crate serde_json;
+extern crate erased_serde;
+#[macro_use]
+extern crate error_chain;
+
+#[macro_use]
+extern crate serde_derive;
+
+
+#[macro_use]
+extern crate slog;
+extern crate slog_json;
+
+extern crate prost;
+#[macro_use]
+extern crate prost_derive;
@@ -1,44 +1,6 @@
-#![allow(dead_code)]
-mod network;
-mod oneshot_server;
-mod planetwars;
-mod protobuf_codec;
-mod utils;
-
-pub mod protocol {
-    include!(concat!(env!("OUT_DIR"), "/mozaic.protocol.rs"));
-}
-
-
-extern crate bytes;
-extern crate hex;
-
-extern crate tokio_core;
-extern crate tokio_io;
-extern crate tokio_process;
-extern crate tokio;
-extern crate tokio_timer;
-#[macro_use]
-extern crate futures;
-
-extern crate serde;
+extern crate mozaic_bot_driver;
 extern crate serde_json;
-extern crate erased_serde;
-#[macro_use]
-extern crate error_chain;
-
-#[macro_use]
-extern crate serde_derive;
-
-
-#[macro_use]
-extern crate slog;
-extern crate slog_json;
-
-extern crate prost;
-#[macro_use]
-extern crate prost_derive;
-
+extern crate tokio;
 
 use std::error::Error;
 use std::io::{Read};
@@ -46,7 +8,7 @@ use std::env;
 use std::path::Path;
 use std::fs::File;
 
-use oneshot_server::{MatchDescription, OneshotServer};
+use mozaic_bot_driver::oneshot_server::{MatchDescription, OneshotServer};
 
 // Load the config and start the game.
 fn main() {
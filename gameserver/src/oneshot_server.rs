@@ -1,5 +1,6 @@
 use std;
 use std::fs::File;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 use futures::{Future, Poll, Async};
@@ -22,6 +23,9 @@ pub struct MatchDescription {
     pub ctrl_token: Vec<u8>,
     pub address: String,
     pub log_file: String,
+    /// Directory with maps that match configs can refer to by name.
+    #[serde(default)]
+    pub map_pool: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -73,6 +77,7 @@ impl Future for OneshotServer {
         let controller = PwMatch::new(
             self.config.ctrl_token.clone(),
             routing_table.clone(),
+            self.config.map_pool.as_ref().map(PathBuf::from),
             logger,
         );
         tokio::spawn(controller.and_then(|_| {
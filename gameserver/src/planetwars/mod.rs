@@ -1,12 +1,15 @@
 
 mod pw_controller;
-mod pw_rules;
 mod pw_config;
 mod pw_protocol;
 mod pw_serializer;
 mod pw_client;
+mod pw_commands;
+mod pw_replay;
+mod pw_strikes;
 
-pub use self::pw_controller::{PwMatch, PlayerId};
-pub use self::pw_rules::PlanetWars;
+pub use self::pw_controller::PwMatch;
+pub use planetwars_rules::{PlanetWars, PlayerId};
 pub use self::pw_config::Config;
-pub use self::pw_protocol::Map;
\ No newline at end of file
+pub use self::pw_protocol::{Map, PlayerAction};
+pub use self::pw_replay::{MatchLog, Replay};
\ No newline at end of file
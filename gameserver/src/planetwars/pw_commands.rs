@@ -0,0 +1,496 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json;
+use planetwars_rules::{PlanetWars, PlayerId, Dispatch};
+
+use utils::client_handler::{ResponseValue, ResponseError};
+
+use super::pw_config::Overdraw;
+use super::pw_protocol::{
+    self as proto,
+    PlayerAction,
+    PlayerCommand,
+    CommandError,
+    DebugInfo,
+};
+
+/// Maps planet names to planet ids.
+pub type PlanetMap = HashMap<String, usize>;
+
+pub fn planet_map(state: &PlanetWars) -> PlanetMap {
+    state.planets.iter().map(|planet| {
+        (planet.name.to_string(), planet.id)
+    }).collect()
+}
+
+/// The outcome of a player's response: the resulting action, and the debug
+/// info the player sent along.
+pub type PlayerResult = (PlayerId, PlayerAction, Option<DebugInfo>);
+
+/// Execute the responses of all players as simultaneous moves.
+/// Every command is validated against the state at the start of the turn, so
+/// no player's commands can influence whether another player's are valid.
+/// The valid commands are then dispatched together, in player order.
+/// Results are returned in player order as well.
+pub fn execute_actions(state: &mut PlanetWars,
+                       planet_map: &PlanetMap,
+                       overdraw: Overdraw,
+                       mut responses: Vec<(PlayerId, ResponseValue)>)
+                       -> Vec<PlayerResult>
+{
+    responses.sort_by_key(|&(player_id, _)| player_id.as_usize());
+
+    let mut results = Vec::new();
+    let mut dispatches = Vec::new();
+    for (player_id, response) in responses {
+        let (action, debug) = validate_action(
+            state,
+            planet_map,
+            player_id,
+            overdraw,
+            response,
+            &mut dispatches,
+        );
+        results.push((player_id, action, debug));
+    }
+
+    for dispatch in dispatches.iter() {
+        state.dispatch(dispatch);
+    }
+    return results;
+}
+
+/// The content of a response as it is written to the log. The debug info in
+/// an action is logged in an entry of its own, so it is left out here.
+pub fn logged_content(message: &[u8]) -> String {
+    let content = match String::from_utf8(message.to_vec()) {
+        Ok(content) => content,
+        Err(_err) => return "invalid utf-8".to_string(),
+    };
+    match serde_json::from_str::<proto::Action>(&content) {
+        Ok(ref action) if action.debug.is_some() => {
+            let action = proto::Action {
+                commands: action.commands.clone(),
+                debug: None,
+            };
+            serde_json::to_string(&action).unwrap()
+        }
+        _ => content,
+    }
+}
+
+/// Validate the commands in a player's response, adding the resulting
+/// dispatches to `dispatches`.
+fn validate_action(state: &PlanetWars,
+                   planet_map: &PlanetMap,
+                   player_id: PlayerId,
+                   overdraw: Overdraw,
+                   response: ResponseValue,
+                   dispatches: &mut Vec<Dispatch>)
+                   -> (PlayerAction, Option<DebugInfo>)
+{
+    // TODO: it would be cool if this could be done with error_chain.
+
+    let message = match response {
+        Err(ResponseError::Timeout) => return (PlayerAction::Timeout, None),
+        Ok(message) => message,
+    };
+
+    let action: proto::Action = match serde_json::from_slice(&message) {
+        Err(err) => {
+            return (PlayerAction::ParseError(err.to_string()), None);
+        }
+        Ok(action) => action,
+    };
+
+    let mut results: Vec<Result<Dispatch, CommandError>> = action.commands
+        .iter()
+        .enumerate()
+        .map(|(num, command)| {
+            let repeated = action.commands[..num].iter().any(|earlier| {
+                earlier.origin == command.origin
+                    && earlier.destination == command.destination
+                    && earlier.ship_count == command.ship_count
+            });
+            if repeated {
+                return Err(CommandError::DuplicateCommand);
+            }
+            parse_command(state, planet_map, player_id, command)
+        })
+        .collect();
+
+    // ships this player already committed, per origin planet
+    let mut spent: HashMap<usize, u64> = HashMap::new();
+    let mut overdrawn: HashSet<usize> = HashSet::new();
+    for result in results.iter_mut() {
+        let (origin, ship_count) = match result {
+            &mut Ok(ref dispatch) => (dispatch.origin, dispatch.ship_count),
+            &mut Err(_) => continue,
+        };
+        let committed = spent.get(&origin).cloned().unwrap_or(0);
+        if committed + ship_count <= state.planets[origin].ship_count() {
+            spent.insert(origin, committed + ship_count);
+        } else {
+            overdrawn.insert(origin);
+            *result = Err(CommandError::Overdrawn);
+        }
+    }
+    if overdraw == Overdraw::RejectAll {
+        for result in results.iter_mut() {
+            let rejected = match result {
+                &mut Ok(ref dispatch) => overdrawn.contains(&dispatch.origin),
+                &mut Err(_) => false,
+            };
+            if rejected {
+                *result = Err(CommandError::Overdrawn);
+            }
+        }
+    }
+
+    let commands = action.commands.into_iter().zip(results).map(|(command, result)| {
+        match result {
+            Ok(dispatch) => {
+                dispatches.push(dispatch);
+                PlayerCommand {
+                    command,
+                    error: None,
+                }
+            },
+            Err(error) => {
+                PlayerCommand {
+                    command,
+                    error: Some(error),
+                }
+            }
+        }
+    }).collect();
+
+    return (PlayerAction::Commands(commands), action.debug);
+}
+
+fn parse_command(state: &PlanetWars,
+                 planet_map: &PlanetMap,
+                 player_id: PlayerId,
+                 mv: &proto::Command)
+                 -> Result<Dispatch, CommandError>
+{
+    let origin_id = *planet_map
+        .get(&mv.origin)
+        .ok_or(CommandError::OriginDoesNotExist)?;
+
+    let target_id = *planet_map
+        .get(&mv.destination)
+        .ok_or(CommandError::DestinationDoesNotExist)?;
+
+    if state.planets[origin_id].owner() != Some(player_id) {
+        return Err(CommandError::OriginNotOwned);
+    }
+
+    if state.planets[origin_id].ship_count() < mv.ship_count {
+        return Err(CommandError::NotEnoughShips);
+    }
+
+    if mv.ship_count == 0 {
+        return Err(CommandError::ZeroShipMove);
+    }
+
+    if origin_id == target_id {
+        return Err(CommandError::SelfTarget);
+    }
+
+    Ok(Dispatch {
+        origin: origin_id,
+        target: target_id,
+        ship_count: mv.ship_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    use planetwars::Config;
+    use planetwars::pw_serializer::serialize;
+
+    const STATE: &str = r#"{
+        "planets": [
+            { "name": "protos", "x": 0, "y": 0, "owner": 1, "ship_count": 10 },
+            { "name": "duteros", "x": 4, "y": 0, "owner": 2, "ship_count": 10 },
+            { "name": "tritos", "x": 0, "y": 4, "owner": 3, "ship_count": 5 },
+            { "name": "tetartos", "x": 4, "y": 4, "ship_count": 3 }
+        ],
+        "expeditions": []
+    }"#;
+
+    fn response(player_num: usize) -> (PlayerId, ResponseValue) {
+        let moves = match player_num {
+            0 => r#"[
+                { "origin": "protos", "destination": "tetartos", "ship_count": 6 },
+                { "origin": "protos", "destination": "duteros", "ship_count": 5 }
+            ]"#,
+            1 => r#"[
+                { "origin": "duteros", "destination": "protos", "ship_count": 10 },
+                { "origin": "duteros", "destination": "tritos", "ship_count": 1 }
+            ]"#,
+            _ => r#"[
+                { "origin": "tritos", "destination": "tetartos", "ship_count": 5 },
+                { "origin": "protos", "destination": "tritos", "ship_count": 1 }
+            ]"#,
+        };
+        let action = format!(r#"{{ "moves": {} }}"#, moves);
+        (PlayerId::new(player_num), Ok(action.into_bytes()))
+    }
+
+    fn game() -> PlanetWars {
+        Config::restore_for_test(STATE, 3)
+    }
+
+    /// The errors in the results of `execute_actions`, in player order.
+    fn errors(results: &[PlayerResult]) -> Vec<Value> {
+        results.iter()
+            .flat_map(|&(_, ref action, _)| match action {
+                &PlayerAction::Commands(ref commands) => commands.iter()
+                    .map(|command| serde_json::to_value(&command.error).unwrap())
+                    .collect::<Vec<_>>(),
+                _ => panic!("no commands"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn moves_are_checked_per_player() {
+        let mut game = game();
+        let planet_map = planet_map(&game);
+        let responses = vec![response(2), response(0), response(1)];
+        let results = execute_actions(
+            &mut game,
+            &planet_map,
+            Overdraw::InOrder,
+            responses,
+        );
+
+        // only the commands that were valid at the start of the turn went out
+        assert_eq!(errors(&results), vec![
+            Value::Null, Value::from("Overdrawn"),
+            Value::Null, Value::from("Overdrawn"),
+            Value::Null, Value::from("OriginNotOwned"),
+        ]);
+        assert_eq!(game.expeditions.len(), 3);
+    }
+
+    /// Errors, planet snapshots and expeditions, see `relabelled_turn`.
+    type Outcome = (Vec<Value>, Vec<Vec<(Option<u64>, u64)>>, Vec<(String, u64, u64)>);
+
+    /// Play the turn of `moves_are_checked_per_player` with the players
+    /// renumbered, the player numbered `i` in `STATE` becoming `labels[i]`.
+    /// Returns the errors of every player and, after one step and once all
+    /// fleets have arrived, the planets and expeditions, all with the
+    /// players numbered as in `STATE` again.
+    fn relabelled_turn(labels: [usize; 3]) -> Outcome {
+        let to_label = |owner: u64| labels[owner as usize - 1] as u64 + 1;
+        let from_label = |owner: u64| {
+            labels.iter().position(|&l| l as u64 + 1 == owner).unwrap() as u64 + 1
+        };
+
+        let mut state: Value = serde_json::from_str(STATE).unwrap();
+        for planet in state["planets"].as_array_mut().unwrap() {
+            if let Some(owner) = planet["owner"].as_u64() {
+                planet["owner"] = Value::from(to_label(owner));
+            }
+        }
+        let mut game = Config::restore_for_test(&state.to_string(), 3);
+        let planet_map = planet_map(&game);
+        let responses = (0..3).map(|player_num| {
+            let (_, response) = response(player_num);
+            (PlayerId::new(labels[player_num]), response)
+        }).collect();
+        let results = execute_actions(
+            &mut game,
+            &planet_map,
+            Overdraw::InOrder,
+            responses,
+        );
+        let by_player: Vec<PlayerResult> = labels.iter()
+            .map(|&label| results.iter()
+                .find(|&&(id, _, _)| id == PlayerId::new(label))
+                .cloned()
+                .unwrap())
+            .collect();
+
+        let planets = |game: &PlanetWars| serialize(game).planets.iter()
+            .map(|planet| (planet.owner.map(from_label), planet.ship_count))
+            .collect::<Vec<_>>();
+        game.step();
+        let mut expeditions: Vec<_> = serialize(&game).expeditions.iter()
+            .map(|exp| (exp.origin.clone(), from_label(exp.owner), exp.ship_count))
+            .collect();
+        // expedition ids follow the order in which players are handled
+        expeditions.sort();
+        let mut snapshots = vec![planets(&game)];
+        for _ in 0..5 {
+            game.step();
+        }
+        assert!(game.expeditions.is_empty());
+        snapshots.push(planets(&game));
+        (errors(&by_player), snapshots, expeditions)
+    }
+
+    #[test]
+    fn player_numbers_do_not_matter() {
+        let outcome = relabelled_turn([0, 1, 2]);
+        let permutations = [
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        for &labels in permutations.iter() {
+            assert_eq!(relabelled_turn(labels), outcome, "labels {:?}", labels);
+        }
+    }
+
+    const DUEL: &str = r#"{
+        "planets": [
+            { "name": "protos", "x": 0, "y": 0, "owner": 1, "ship_count": 10 },
+            { "name": "duteros", "x": 4, "y": 0, "owner": 2, "ship_count": 10 },
+            { "name": "tritos", "x": 2, "y": 0, "ship_count": 3 }
+        ],
+        "expeditions": []
+    }"#;
+
+    /// `raider` sends every ship on its home planet to the neutral planet,
+    /// while `attacker` sends ships to that same home planet. Returns the
+    /// errors and, once all fleets have arrived, the owner and ship count of
+    /// every planet.
+    fn raid(raider: usize, attacker: usize)
+        -> (Vec<Value>, Vec<(Option<u64>, u64)>)
+    {
+        let homes = ["protos", "duteros"];
+        let action = |moves: String| {
+            Ok(format!(r#"{{ "moves": [{}] }}"#, moves).into_bytes())
+        };
+        let responses = vec![
+            (PlayerId::new(raider), action(format!(
+                r#"{{ "origin": "{}", "destination": "tritos", "ship_count": 10 }}"#,
+                homes[raider],
+            ))),
+            (PlayerId::new(attacker), action(format!(
+                r#"{{ "origin": "{}", "destination": "{}", "ship_count": 6 }}"#,
+                homes[attacker],
+                homes[raider],
+            ))),
+        ];
+
+        let mut game = Config::restore_for_test(DUEL, 2);
+        let planet_map = planet_map(&game);
+        let results = execute_actions(
+            &mut game,
+            &planet_map,
+            Overdraw::InOrder,
+            responses,
+        );
+        for _ in 0..4 {
+            game.step();
+        }
+        let planets = serialize(&game).planets.iter()
+            .map(|planet| (planet.owner, planet.ship_count))
+            .collect();
+        (errors(&results), planets)
+    }
+
+    #[test]
+    fn moves_are_simultaneous() {
+        // the attack on protos is checked against the start of the turn, and
+        // only arrives once protos has been emptied.
+        let (errors, planets) = raid(0, 1);
+        assert_eq!(errors, vec![Value::Null, Value::Null]);
+        assert_eq!(planets, vec![(Some(2), 6), (Some(2), 4), (Some(1), 7)]);
+
+        // with the roles swapped, the outcome is mirrored
+        let (errors, planets) = raid(1, 0);
+        assert_eq!(errors, vec![Value::Null, Value::Null]);
+        assert_eq!(planets, vec![(Some(1), 4), (Some(1), 6), (Some(2), 7)]);
+    }
+
+    /// Validate the moves of the first player, and return the errors and the
+    /// number of dispatches.
+    fn validate(moves: &str, overdraw: Overdraw) -> (Vec<Value>, usize) {
+        let game = game();
+        let planet_map = planet_map(&game);
+        let action = format!(r#"{{ "moves": {} }}"#, moves);
+        let response = Ok(action.into_bytes());
+
+        let mut dispatches = Vec::new();
+        let (action, _debug) = validate_action(
+            &game,
+            &planet_map,
+            PlayerId::new(0),
+            overdraw,
+            response,
+            &mut dispatches,
+        );
+        let action = serde_json::to_value(&action).unwrap();
+        let errors = action["value"].as_array().unwrap().iter()
+            .map(|command| command["error"].clone())
+            .collect();
+        (errors, dispatches.len())
+    }
+
+    #[test]
+    fn debug_info_is_logged_once() {
+        let content = logged_content(br#"{
+            "moves": [
+                { "origin": "protos", "destination": "duteros", "ship_count": 1 }
+            ],
+            "debug": { "messages": ["attack!"] }
+        }"#);
+        let logged: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(logged["debug"], Value::Null);
+        assert_eq!(logged["moves"][0]["origin"], Value::from("protos"));
+
+        // anything else is logged as it was sent
+        let garbage = r#"{ "moves": 3, "debug": {} }"#;
+        assert_eq!(logged_content(garbage.as_bytes()), garbage);
+        assert_eq!(logged_content(&[0xff]), "invalid utf-8");
+    }
+
+    #[test]
+    fn refused_moves() {
+        let (errors, dispatched) = validate(r#"[
+            { "origin": "protos", "destination": "protos", "ship_count": 1 },
+            { "origin": "protos", "destination": "tetartos", "ship_count": 2 },
+            { "origin": "protos", "destination": "tetartos", "ship_count": 2 },
+            { "origin": "protos", "destination": "tetartos", "ship_count": 11 }
+        ]"#, Overdraw::InOrder);
+        assert_eq!(errors, vec![
+            Value::from("SelfTarget"),
+            Value::Null,
+            Value::from("DuplicateCommand"),
+            Value::from("NotEnoughShips"),
+        ]);
+        assert_eq!(dispatched, 1);
+    }
+
+    #[test]
+    fn overdraw_policies() {
+        let moves = r#"[
+            { "origin": "protos", "destination": "tetartos", "ship_count": 6 },
+            { "origin": "protos", "destination": "duteros", "ship_count": 5 },
+            { "origin": "protos", "destination": "tritos", "ship_count": 4 }
+        ]"#;
+
+        let (errors, dispatched) = validate(moves, Overdraw::InOrder);
+        assert_eq!(errors, vec![
+            Value::Null,
+            Value::from("Overdrawn"),
+            Value::Null,
+        ]);
+        assert_eq!(dispatched, 2);
+
+        let (errors, dispatched) = validate(moves, Overdraw::RejectAll);
+        assert_eq!(errors, vec![Value::from("Overdrawn"); 3]);
+        assert_eq!(dispatched, 0);
+    }
+}
@@ -1,27 +1,238 @@
-use std::fs::File;
-use std::io::Read;
 use std::io;
+use std::path::Path;
+use std::sync::Arc;
 
-use serde_json;
+use map_generator::{self, Bound};
+use slog;
+use erased_serde;
+use planetwars_rules::*;
 
-use super::PlayerId;
 use super::pw_protocol as proto;
-use super::pw_rules::*;
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    pub map_file: String,
+    #[serde(flatten)]
+    pub map: MapSource,
     pub max_turns: u64,
+    /// Seed for all randomness in the match. When absent, the server picks
+    /// one; it is always recorded in the match log.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub combat: CombatModel,
+    #[serde(default)]
+    pub movement: Movement,
+    #[serde(default)]
+    pub neutrals: Neutrals,
+    /// What happens to start planets of players that are not in the match.
+    #[serde(default)]
+    pub unused_starts: UnusedStarts,
+    /// Play a rematch on the same map for every other rotation of the
+    /// seats, so that every player plays every start position once.
+    #[serde(default)]
+    pub swap_seats: bool,
+    /// Wait for all players to connect before the first turn. Without it,
+    /// the match starts right away.
+    #[serde(default)]
+    pub wait_for_players: Option<WaitForPlayers>,
+    /// When a misbehaving bot is disqualified.
+    #[serde(default)]
+    pub strikes: StrikeRules,
+    /// What happens to moves that send more ships from a planet than it has.
+    #[serde(default)]
+    pub overdraw: Overdraw,
+    /// What players see of the expeditions of their opponents.
+    #[serde(default)]
+    pub expedition_visibility: Visibility,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitForPlayers {
+    /// How long to wait, in milliseconds.
+    pub timeout: u64,
+    /// What happens when the time is up.
+    #[serde(default)]
+    pub missing_players: MissingPlayers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingPlayers {
+    /// Players that did not connect lose; the others play.
+    Forfeit,
+    /// The match does not take place.
+    Cancel,
+}
+
+impl Default for MissingPlayers {
+    fn default() -> Self {
+        MissingPlayers::Forfeit
+    }
+}
+
+/// What players see of enemy expeditions while they are underway. By
+/// default, they see everything.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Visibility {
+    /// Leave out where enemy expeditions are going.
+    #[serde(default)]
+    pub hide_destinations: bool,
+    /// Round the ship counts of enemy expeditions up to a multiple of this.
+    #[serde(default)]
+    pub ship_count_step: Option<u64>,
+}
+
+impl Visibility {
+    /// The ship count an enemy expedition shows.
+    pub fn ship_count(&self, ship_count: u64) -> u64 {
+        match self.ship_count_step {
+            Some(step) if step > 1 => (ship_count + step - 1) / step * step,
+            _ => ship_count,
+        }
+    }
+}
+
+/// How to deal with moves from the same planet that send more ships in total
+/// than the planet has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overdraw {
+    /// Moves are carried out in the order they were sent, as long as there
+    /// are ships left; the ones that no longer fit are rejected.
+    InOrder,
+    /// None of the moves from the planet are carried out.
+    RejectAll,
+}
+
+impl Default for Overdraw {
+    fn default() -> Self {
+        Overdraw::InOrder
+    }
+}
+
+/// Limits on how often a bot can misbehave in a round. A bot that exceeds
+/// one of them is disqualified. All limits are off by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrikeRules {
+    /// Timeouts in a row.
+    #[serde(default)]
+    pub max_consecutive_timeouts: Option<u64>,
+    /// Responses that could not be parsed.
+    #[serde(default)]
+    pub max_parse_errors: Option<u64>,
+    /// The largest fraction of commands that may be invalid.
+    #[serde(default)]
+    pub max_invalid_ratio: Option<f64>,
+    /// The number of commands a bot has to send before the ratio applies,
+    /// so that a single bad command early on does not count for much.
+    #[serde(default = "default_min_commands")]
+    pub min_commands: u64,
+}
+
+fn default_min_commands() -> u64 {
+    10
+}
+
+impl Default for StrikeRules {
+    fn default() -> Self {
+        StrikeRules {
+            max_consecutive_timeouts: None,
+            max_parse_errors: None,
+            max_invalid_ratio: None,
+            min_commands: default_min_commands(),
+        }
+    }
+}
+
+/// Where the map of a match comes from. A match config holds exactly one of
+/// these keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MapSource {
+    /// Path to a map file on the server.
+    #[serde(rename = "map_file")]
+    File(String),
+    /// The map itself.
+    #[serde(rename = "map")]
+    Inline(proto::Map),
+    /// Name of a map in the map pool directory of the server, without the
+    /// `.json` extension.
+    #[serde(rename = "map_name")]
+    Pool(String),
+    /// Generate a map for the players in the match, from the match seed.
+    #[serde(rename = "generate_map")]
+    Generated(map_generator::Config),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnusedStarts {
+    /// The planet keeps its ships, but they become neutral.
+    Neutral,
+    /// The planet is removed from the map.
+    Removed,
+}
+
+impl Default for UnusedStarts {
+    fn default() -> Self {
+        UnusedStarts::Neutral
+    }
 }
 
 impl Config {
-    pub fn create_game(&self, num_players: usize) -> PlanetWars {
+    /// Set up a new game. Maps referred to by name are looked up in
+    /// `map_pool`.
+    pub fn create_game(&self, num_players: usize, map_pool: Option<&Path>)
+        -> io::Result<PlanetWars>
+    {
+        let planets = self.load_map(num_players, map_pool)?;
+        return Ok(self.build_game(num_players, planets));
+    }
+
+    /// Rebuild a game from a serialized state, as found in a match log.
+    /// Player numbers in the state should not be rotated.
+    pub fn restore_game(&self,
+                        num_players: usize,
+                        state: &proto::State,
+                        turn_num: u64)
+                        -> PlanetWars
+    {
+        let mut state = state.clone();
+        state.num_players = num_players as u64;
+        state.turn_num = turn_num;
+        state.max_turns = self.max_turns;
+        return PlanetWars::from_state(&state, self.rules())
+            .unwrap_or_else(|err| panic!("invalid game state: {}", err));
+    }
+
+    /// Restore a game from a state in JSON, playing by the default rules.
+    #[cfg(test)]
+    pub fn restore_for_test(state_json: &str, num_players: usize)
+        -> PlanetWars
+    {
+        let config: Config = ::serde_json::from_str(
+            r#"{ "map_file": "", "max_turns": 10 }"#
+        ).unwrap();
+        let state = ::serde_json::from_str(state_json).unwrap();
+        config.restore_game(num_players, &state, 0)
+    }
+
+    /// The rules the game is played by.
+    pub fn rules(&self) -> Rules {
+        Rules {
+            combat: self.combat,
+            movement: self.movement.clone(),
+            neutrals: self.neutrals,
+        }
+    }
+
+    fn build_game(&self, num_players: usize, planets: Vec<Planet>)
+        -> PlanetWars
+    {
         let players = (0..num_players)
             .map(|id| Player { id: PlayerId::new(id), alive: true })
             .collect();
-        let planets = self.load_map(num_players);
-        
+
         PlanetWars {
             players: players,
             planets: planets,
@@ -29,14 +240,23 @@ impl Config {
             expedition_num: 0,
             turn_num: 0,
             max_turns: self.max_turns,
+            rules: Arc::new(self.rules()),
         }
     }
     
-    fn load_map(&self, num_players: usize) -> Vec<Planet> {
-        let map = self.read_map().expect("[PLANET_WARS] reading map failed");
-        
-        return map.planets
+    fn load_map(&self, num_players: usize, map_pool: Option<&Path>)
+        -> io::Result<Vec<Planet>>
+    {
+        let map = self.read_map(num_players, map_pool)?;
+
+        let planets = map.planets
             .into_iter()
+            .filter(|planet| {
+                let unused = planet.owner.map_or(false, |num| {
+                    num as usize > num_players
+                });
+                !(unused && self.unused_starts == UnusedStarts::Removed)
+            })
             .enumerate()
             .map(|(num, planet)| {
             let mut fleets = Vec::new();
@@ -50,27 +270,162 @@ impl Config {
                         None
                     }
                 });
-            if planet.ship_count > 0 {
+                let ship_count = match owner {
+                    Some(_) => planet.ship_count,
+                    None => self.neutrals.cap(planet.ship_count),
+                };
+            if ship_count > 0 {
                 fleets.push(Fleet {
                     owner: owner,
-                    ship_count: planet.ship_count,
+                    ship_count: ship_count,
                 });
             }
             return Planet {
                 id: num,
-                name: planet.name,
+                name: planet.name.into(),
                 x: planet.x,
                 y: planet.y,
+                growth_rate: planet.growth_rate,
+                radius: planet.radius,
                 fleets: fleets,
             };
         }).collect();
+        return Ok(planets);
+    }
+
+    /// Check the settings that can be checked without a map, such as the
+    /// parameters for generating one.
+    pub fn validate(&self, num_players: usize) -> Result<(), String> {
+        if let MapSource::Generated(ref generator) = self.map {
+            for_players(generator, num_players).validate()
+                .map_err(|msg| format!("invalid generate_map: {}", msg))?;
+        }
+        return Ok(());
+    }
+
+    /// Describe settings that can never take effect in given game.
+    pub fn warnings(&self, state: &PlanetWars) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if self.neutrals.growth {
+            let can_grow = state.planets.iter().any(|planet| {
+                planet.owner().is_none()
+                    && planet.growth_rate > 0
+                    && self.neutrals.cap(planet.ship_count() + 1) > planet.ship_count()
+            });
+            if !can_grow {
+                warnings.push(
+                    "neutral growth is enabled, but no neutral planet can grow"
+                        .to_string()
+                );
+            }
+        }
+
+        for bracket in self.movement.speeds.iter() {
+            if bracket.speed <= 0.0 || bracket.speed.is_nan() {
+                warnings.push(format!(
+                    "fleets of {} ships or more can never reach their destination",
+                    bracket.min_ships,
+                ));
+            }
+        }
+
+        if let Some(ratio) = self.strikes.max_invalid_ratio {
+            if !(ratio >= 0.0 && ratio < 1.0) {
+                warnings.push(format!(
+                    "an invalid command ratio of {} never disqualifies anyone",
+                    ratio,
+                ));
+            }
+        }
+
+        return warnings;
+    }
+
+    fn read_map(&self, num_players: usize, map_pool: Option<&Path>)
+        -> io::Result<proto::Map>
+    {
+        match self.map {
+            MapSource::File(ref path) => read_map_file(Path::new(path)),
+            MapSource::Inline(ref map) => Ok(map.clone()),
+            MapSource::Pool(ref name) => {
+                let pool = map_pool.ok_or_else(|| invalid_input(
+                    "this server has no map pool".to_string()
+                ))?;
+                // names may not point outside of the pool
+                let plain = !name.is_empty()
+                    && !name.starts_with('.')
+                    && !name.contains(|c| c == '/' || c == '\\');
+                if !plain {
+                    return Err(invalid_input(
+                        format!("invalid map name: {}", name)
+                    ));
+                }
+                read_map_file(&pool.join(format!("{}.json", name)))
+            }
+            MapSource::Generated(ref generator) => {
+                let generator = for_players(generator, num_players);
+                // the server always sets a seed before creating the game
+                let seed = self.seed.unwrap_or(0);
+                map_generator::create_seeded_map(&generator, seed)
+                    .map_err(|msg| invalid_input(
+                        format!("could not generate map: {}", msg)
+                    ))
+            }
+        }
+    }
+}
+
+/// The generator config for a map with a start planet for every player.
+fn for_players(generator: &map_generator::Config, num_players: usize)
+    -> map_generator::Config
+{
+    let mut generator = generator.clone();
+    generator.player_amount = Bound {
+        min: num_players,
+        max: num_players + 1,
+    };
+    return generator;
+}
+
+fn read_map_file(path: &Path) -> io::Result<proto::Map> {
+    proto::Map::read(path).map_err(|err| io::Error::new(
+        err.kind(),
+        format!("could not read map {}: {}", path.display(), err),
+    ))
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+impl slog::Value for Config {
+    fn serialize(&self,
+                 _record: &slog::Record,
+                 key: slog::Key,
+                 serializer: &mut slog::Serializer)
+                 -> slog::Result
+    {
+        serializer.emit_serde(key, self)
+    }
+}
+
+impl slog::KV for Config {
+    fn serialize(&self,
+                 _record: &slog::Record,
+                 serializer: &mut slog::Serializer)
+                 -> slog::Result
+    {
+        serializer.emit_serde("config", self)
+    }
+}
+
+impl slog::SerdeValue for Config {
+    fn as_serde(&self) -> &erased_serde::Serialize {
+        self
     }
 
-    fn read_map(&self) -> io::Result<proto::Map> {
-        let mut file = File::open(&self.map_file)?;
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)?;
-        let map = serde_json::from_str(&buf)?;
-        return Ok(map);
+    fn to_sendable(&self) -> Box<slog::SerdeValue + Send + 'static> {
+        Box::new(self.clone())
     }
 }
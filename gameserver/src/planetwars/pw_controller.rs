@@ -1,14 +1,25 @@
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
 use std::mem;
 
 use tokio;
+use tokio::timer::Delay;
 use futures::{Future, Poll, Async, Stream};
 use futures::sync::mpsc::{self, UnboundedSender, UnboundedReceiver};
 use prost::Message as ProtobufMessage;
+use rand::{self, Rng, SeedableRng, Isaac64Rng};
+use planetwars_rules::{PlanetWars, PlayerId};
 use protocol::LobbyMessage;
-use protocol::lobby_message;
+use protocol::lobby_message::{self, LobbyError};
+use protocol::lobby_message::lobby_error::Kind as LobbyErrorKind;
+use protocol::lobby_message::{
+    add_player_response,
+    remove_player_response,
+    start_game_response,
+    control_response,
+};
 
 use utils::client_handler::{
     ClientId,
@@ -23,60 +34,42 @@ use utils::client_handler::{
 use network::router::RoutingTable;
 
 use super::Config;
-use super::pw_rules::{PlanetWars, Dispatch};
+use super::pw_config::{MissingPlayers, Visibility};
 use super::pw_serializer::{serialize, serialize_rotated};
-use super::pw_protocol::{
-    self as proto,
-    PlayerAction,
-    PlayerCommand, 
-    CommandError,
+use super::pw_commands::{
+    execute_actions,
+    logged_content,
+    planet_map,
+    PlanetMap,
 };
+use super::pw_strikes::Referee;
+use super::pw_protocol::{self as proto, PlayerAction};
 
 use slog;
 use serde_json;
 
-// TODO: find a better place for this
-#[derive(PartialEq, Clone, Copy, Eq, Hash, Serialize, Deserialize, Debug)]
-pub struct PlayerId {
-    id: usize,
-}
-
-impl PlayerId {
-    pub fn new(id: usize) -> PlayerId {
-        PlayerId {
-            id
-        }
-    }
-
-    pub fn as_usize(&self) -> usize {
-        self.id
-    }
-}
-
-impl slog::KV for PlayerId {
-    fn serialize(&self,
-                 _record: &slog::Record,
-                 serializer: &mut slog::Serializer)
-                 -> slog::Result
-    {
-        serializer.emit_usize("player_id", self.as_usize())
-    }
-}
-
 pub struct Player {
     id: PlayerId,
     handle: ClientHandle,
+    info: proto::PlayerInfo,
 }
 
 impl Player {
-    fn prompt(&mut self, state: &PlanetWars, deadline: Instant) {
-        let s = self.serialized_state(state);
+    fn prompt(&mut self,
+              state: &PlanetWars,
+              visibility: Visibility,
+              deadline: Instant)
+    {
+        let mut s = self.serialized_state(state, visibility);
+        let time_left = deadline.checked_duration_since(Instant::now())
+            .unwrap_or(Duration::from_secs(0));
+        s.time_budget = Some(time_left.as_millis() as u64);
         self.request(proto::ServerMessage::GameState(s), deadline);
-
     }
 
     fn send_final_state(&mut self, state: &PlanetWars) {
-        let s = self.serialized_state(state);
+        // the game is over, so there is nothing left to hide
+        let s = self.serialized_state(state, Visibility::default());
         self.send(proto::ServerMessage::FinalState(s));
     }
 
@@ -84,9 +77,18 @@ impl Player {
         self.send(proto::ServerMessage::PlayerAction(action));
     }
 
-    fn serialized_state(&self, state: &PlanetWars) -> proto::State {
+    fn send_game_info(&mut self, info: &proto::GameInfo) {
+        // like in the game states, every player sees itself as player 1
+        let mut players = info.players.clone();
+        players.rotate_left(self.id.as_usize());
+        self.send(proto::ServerMessage::GameInfo(proto::GameInfo { players }));
+    }
+
+    fn serialized_state(&self, state: &PlanetWars, visibility: Visibility)
+        -> proto::State
+    {
         let offset = state.players.len() - self.id.as_usize();
-        return serialize_rotated(state, offset);
+        return serialize_rotated(state, offset, visibility);
     }
 
     fn request(&mut self, msg: proto::ServerMessage, deadline: Instant) {
@@ -116,6 +118,7 @@ enum PwMatchState {
 impl PwMatch {
     pub fn new(ctrl_token: Vec<u8>,
                routing_table: Arc<Mutex<RoutingTable>>,
+               map_pool: Option<PathBuf>,
                logger: slog::Logger)
                -> Self
     {
@@ -125,6 +128,7 @@ impl PwMatch {
             ctrl_token,
             routing_table,
             snd.clone(),
+            map_pool,
             logger
         );
 
@@ -146,6 +150,18 @@ impl Future for PwMatch {
 
     fn poll(&mut self) -> Poll<(), ()> {
         loop {
+            let finished = match self.state {
+                PwMatchState::Playing(ref mut pw_controller) => {
+                    pw_controller.poll_connect_timeout();
+                    pw_controller.is_finished()
+                }
+                _ => false,
+            };
+            if finished {
+                self.state = PwMatchState::Finished;
+                return Ok(Async::Ready(()));
+            }
+
             let event = try_ready!(self.event_channel.poll())
                 .expect("event channel closed");
 
@@ -154,7 +170,7 @@ impl Future for PwMatch {
                 PwMatchState::Lobby(mut lobby) => {
                     lobby.handle_event(event);
                     
-                    if lobby.game_data.is_some() {
+                    if lobby.game.is_some() {
                         let pw_controller = PwController::new(lobby);
                         self.state = PwMatchState::Playing(pw_controller);
                     } else {
@@ -165,7 +181,7 @@ impl Future for PwMatch {
                 PwMatchState::Playing(mut pw_controller) => {
                     pw_controller.handle_event(event);
 
-                    if pw_controller.state.is_finished() {
+                    if pw_controller.is_finished() {
                         self.state = PwMatchState::Finished;
                         // TODO: how do we properly handle this?
                         return Ok(Async::Ready(()));
@@ -186,16 +202,28 @@ pub struct Lobby {
 
     routing_table: Arc<Mutex<RoutingTable>>,
     event_channel_handle: UnboundedSender<Event>,
+    /// Directory that holds the maps a match config can refer to by name.
+    map_pool: Option<PathBuf>,
 
-    game_data: Option<Vec<u8>>,
-    players: HashMap<ClientId, ClientHandle>,
+    game: Option<(Config, PlanetWars)>,
+    players: HashMap<ClientId, LobbyPlayer>,
+    connected: HashSet<ClientId>,
     client_counter: u64,
 }
 
+/// A player that was added to the lobby.
+struct LobbyPlayer {
+    handle: ClientHandle,
+    info: proto::PlayerInfo,
+    /// The requested seat, counting from 1; 0 for any seat.
+    seat: u64,
+}
+
 impl Lobby {
     fn new(ctrl_token: Vec<u8>,
            routing_table: Arc<Mutex<RoutingTable>>,
            event_channel_handle: UnboundedSender<Event>,
+           map_pool: Option<PathBuf>,
            logger: slog::Logger)
            -> Self
     {
@@ -214,9 +242,11 @@ impl Lobby {
 
             routing_table,
             event_channel_handle,
+            map_pool,
 
-            game_data: None,
+            game: None,
             players: HashMap::new(),
+            connected: HashSet::new(),
             // start counter at 1, because 0 is the control client
             client_counter: 1,
         }
@@ -228,7 +258,12 @@ impl Lobby {
         return ClientId(num);
     }
 
-    fn add_player(&mut self, connection_token: Vec<u8>) -> ClientId {
+    fn add_player(&mut self,
+                  connection_token: Vec<u8>,
+                  info: proto::PlayerInfo,
+                  seat: u64)
+                  -> ClientId
+    {
         let client_id = self.generate_client_id();
         let (handle, handler) = ClientHandler::new(
             client_id,
@@ -236,37 +271,95 @@ impl Lobby {
             self.routing_table.clone(),
             self.event_channel_handle.clone(),
         );
-        self.players.insert(client_id, handle);
+        self.players.insert(client_id, LobbyPlayer { handle, info, seat });
         tokio::spawn(handler);
         return client_id;
     }
 
     fn remove_player(&mut self, client_id: ClientId) {
         self.players.remove(&client_id);
+        self.connected.remove(&client_id);
     }
 
     fn handle_message(&mut self, message_id: MessageId, content: Vec<u8>) {
-        let message = match LobbyMessage::decode(content) {
-            Err(_) => { return; }, // skip
-            Ok(message) => message
+        let payload = match decode_lobby_message(content) {
+            Ok(payload) => payload,
+            Err(error) => {
+                let response = lobby_message::ErrorResponse {
+                    error: Some(error),
+                };
+                self.ctrl_handle.respond(message_id, encode_message(&response));
+                return;
+            }
         };
-        match message.payload {
-            None => { } // skip
-            Some(lobby_message::Payload::AddPlayer(request)) => {
-                let ClientId(client_num) = self.add_player(request.token);
+        match payload {
+            lobby_message::Payload::AddPlayer(request) => {
+                let info = proto::PlayerInfo {
+                    name: request.name,
+                    bot_version: request.bot_version,
+                    owner_id: request.owner_id,
+                };
+                let ClientId(client_num) = self.add_player(
+                    request.token,
+                    info,
+                    request.seat,
+                );
                 let response = lobby_message::AddPlayerResponse {
-                    client_id: client_num,
+                    response: Some(
+                        add_player_response::Response::ClientId(client_num)
+                    ),
                 };
                 self.ctrl_handle.respond(message_id, encode_message(&response));
             }
-            Some(lobby_message::Payload::RemovePlayer(request)) => {
+            lobby_message::Payload::RemovePlayer(request) => {
                 self.remove_player(ClientId(request.client_id));
-                let response = lobby_message::RemovePlayerResponse {};
+                let response = lobby_message::RemovePlayerResponse {
+                    response: Some(remove_player_response::Response::Success(
+                        lobby_message::RemovePlayerSuccess {}
+                    )),
+                };
+                self.ctrl_handle.respond(message_id, encode_message(&response));
+            }
+            lobby_message::Payload::StartGame(request) => {
+                let map_pool = self.map_pool.as_ref().map(|p| p.as_path());
+                let seats: Vec<u64> = self.players.values()
+                    .map(|player| player.seat)
+                    .collect();
+                let result = create_game(&request.payload, &seats, map_pool);
+                let response = match result {
+                    Ok((conf, state)) => {
+                        let warnings = conf.warnings(&state);
+                        for warning in warnings.iter() {
+                            warn!(self.logger, "config warning";
+                                "warning" => warning,
+                            );
+                        }
+                        self.game = Some((conf, state));
+                        start_game_response::Response::Success(
+                            lobby_message::StartGameSuccess { warnings }
+                        )
+                    }
+                    Err(error) => {
+                        warn!(self.logger, "start game failed";
+                            "error" => &error.message,
+                        );
+                        start_game_response::Response::Error(error)
+                    }
+                };
+                let response = lobby_message::StartGameResponse {
+                    response: Some(response),
+                };
                 self.ctrl_handle.respond(message_id, encode_message(&response));
             }
-            Some(lobby_message::Payload::StartGame(request)) => {
-                self.game_data = Some(request.payload);
-                let response = lobby_message::StartGameResponse {};
+            lobby_message::Payload::Pause(_) |
+            lobby_message::Payload::Resume(_) |
+            lobby_message::Payload::Step(_) |
+            lobby_message::Payload::Abort(_) => {
+                let error = lobby_error(
+                    LobbyErrorKind::NotStarted,
+                    "the match has not started yet".to_string(),
+                );
+                let response = control_result(Err(error));
                 self.ctrl_handle.respond(message_id, encode_message(&response));
             }
         }
@@ -276,6 +369,7 @@ impl Lobby {
         match event.content {
             EventContent::Connected => {
                 if self.players.contains_key(&event.client_id) {
+                    self.connected.insert(event.client_id);
                     let ClientId(client_num) = event.client_id;
                     let msg = proto::ControlMessage::PlayerConnected {
                         player_id: client_num as u64
@@ -286,6 +380,7 @@ impl Lobby {
             },
             EventContent::Disconnected => {
                 if self.players.contains_key(&event.client_id) {
+                    self.connected.remove(&event.client_id);
                     let ClientId(client_num) = event.client_id;
                     let msg = proto::ControlMessage::PlayerDisconnected {
                         player_id: client_num as u64
@@ -305,45 +400,80 @@ impl Lobby {
 }
 
 pub struct PwController {
+    conf: Config,
+    /// The state every round starts from.
+    initial_state: PlanetWars,
+    /// The number of rounds left after this one.
+    rematches: usize,
+
     state: PlanetWars,
-    planet_map: HashMap<String, usize>,
+    planet_map: PlanetMap,
     logger: slog::Logger,
     ctrl_handle: ClientHandle,
 
     client_player: HashMap<ClientId, PlayerId>,
     players: HashMap<PlayerId, Player>,
 
+    /// Players that are connected, while waiting for the others.
+    connected: HashSet<ClientId>,
+    /// Set while the first turn waits for players to connect.
+    connect_timeout: Option<Delay>,
+    /// Players that sit out every round, because they did not connect.
+    forfeited: HashSet<ClientId>,
+    /// Players that were disqualified, and why. They sit out the rounds
+    /// that follow as well.
+    disqualified: HashMap<ClientId, String>,
+    referee: Referee,
+    cancelled: bool,
+    /// Players are not prompted for a new turn while the match is paused.
+    paused: bool,
+
     waiting_for: HashSet<PlayerId>,
     commands: HashMap<PlayerId, ResponseValue>,
 }
 
 impl PwController {
     pub fn new(lobby: Lobby) -> Self {
-        // TODO: neat error handling
-        let raw_conf = lobby.game_data.as_ref()
-            .expect("game data not present in lobby");
-        let conf: Config = serde_json::from_slice(raw_conf)
-            .expect("could not parse game data");
-        let state = conf.create_game(lobby.players.len());
+        let (conf, state) = lobby.game
+            .expect("game not created in lobby");
+        let mut rng = Isaac64Rng::from_seed(&[conf.seed.unwrap()]);
+
+        let planet_map = planet_map(&state);
 
-        let planet_map = state.planets.iter().map(|planet| {
-            (planet.name.clone(), planet.id)
-        }).collect();
+        // Sort the players before shuffling, so that the assignment does not
+        // depend on hashmap iteration order.
+        let mut lobby_players: Vec<_> = lobby.players.into_iter().collect();
+        lobby_players.sort_by_key(|&(ClientId(client_num), _)| client_num);
+        rng.shuffle(&mut lobby_players);
+
+        let requested: Vec<u64> = lobby_players.iter()
+            .map(|&(_, ref player)| player.seat)
+            .collect();
+        let seats = assign_seats(&requested);
 
         let mut client_player = HashMap::new();
         let mut players = HashMap::new();
-
-        let iter = lobby.players.into_iter().enumerate();
-        for (player_num, (client_id, client_handle)) in iter {
-            let player_id = PlayerId::new(player_num);
+        let iter = lobby_players.into_iter().zip(seats);
+        for ((client_id, lobby_player), player_id) in iter {
             client_player.insert(client_id, player_id);
             players.insert(player_id, Player {
                 id: player_id,
-                handle: client_handle,
+                handle: lobby_player.handle,
+                info: lobby_player.info,
             });
         }
 
+        let rematches = if conf.swap_seats { players.len() - 1 } else { 0 };
+        let connect_timeout = conf.wait_for_players.as_ref().map(|wait| {
+            Delay::new(Instant::now() + Duration::from_millis(wait.timeout))
+        });
+
+        let referee = Referee::new(conf.strikes.clone());
         let mut controller = PwController {
+            conf,
+            initial_state: state.clone(),
+            rematches,
+
             state,
             planet_map,
             players,
@@ -351,17 +481,149 @@ impl PwController {
             logger: lobby.logger,
             ctrl_handle: lobby.ctrl_handle,
 
+            connected: lobby.connected,
+            connect_timeout,
+            forfeited: HashSet::new(),
+            disqualified: HashMap::new(),
+            referee,
+            cancelled: false,
+            paused: false,
+
             waiting_for: HashSet::new(),
             commands: HashMap::new(),
         };
-        controller.start_game();
+        controller.start_when_connected();
         return controller;
     }
 
+    pub fn is_finished(&self) -> bool {
+        self.cancelled || self.state.is_finished()
+    }
+
+    /// Start the game, unless we are still waiting for players.
+    fn start_when_connected(&mut self) {
+        if self.connect_timeout.is_none() {
+            return self.start_game();
+        }
+        let all_connected = self.players.values()
+            .all(|player| self.connected.contains(&player.handle.id()));
+        if all_connected {
+            self.connect_timeout = None;
+            self.start_game();
+        }
+    }
+
+    /// Deal with players that did not connect in time, once the time is up.
+    fn poll_connect_timeout(&mut self) {
+        let expired = match self.connect_timeout {
+            Some(ref mut delay) => match delay.poll() {
+                Ok(Async::Ready(())) => true,
+                Ok(Async::NotReady) => false,
+                Err(err) => {
+                    // a broken timer should not take down the server; act
+                    // as if the time is up
+                    warn!(self.logger, "connect timer failed";
+                        "error" => err.to_string(),
+                    );
+                    true
+                }
+            },
+            None => false,
+        };
+        if !expired {
+            return;
+        }
+        self.connect_timeout = None;
+
+        let mut missing: Vec<u64> = self.players.values()
+            .map(|player| player.handle.id())
+            .filter(|client_id| !self.connected.contains(client_id))
+            .map(|ClientId(client_num)| client_num)
+            .collect();
+        missing.sort();
+
+        let policy = self.conf.wait_for_players.as_ref()
+            .map_or(MissingPlayers::Forfeit, |wait| wait.missing_players);
+        match policy {
+            MissingPlayers::Forfeit => {
+                info!(self.logger, "players forfeited";
+                    "client_ids" => format!("{:?}", missing),
+                );
+                self.forfeited = missing.iter()
+                    .map(|&client_num| ClientId(client_num))
+                    .collect();
+                self.notify(proto::ControlMessage::PlayersForfeited {
+                    player_ids: missing,
+                });
+                self.start_game();
+            }
+            MissingPlayers::Cancel => {
+                let reason = format!(
+                    "players {:?} did not connect in time",
+                    missing,
+                );
+                info!(self.logger, "match cancelled"; "reason" => &reason);
+                self.notify(proto::ControlMessage::MatchCancelled { reason });
+                self.cancelled = true;
+            }
+        }
+    }
+
 
     fn start_game(&mut self) {
+        // every round is logged as a match of its own
+        info!(self.logger, "match config"; self.conf.clone());
+
+        let mut assigned: Vec<&Player> = self.players.values().collect();
+        assigned.sort_by_key(|player| player.id.as_usize());
+        for player in assigned.iter() {
+            let ClientId(client_num) = player.handle.id();
+            info!(self.logger, "player assigned";
+                "player_id" => player.id.as_usize(),
+                "client_id" => client_num,
+                "name" => &player.info.name,
+            );
+        }
+
+        let game_info = proto::GameInfo {
+            players: assigned.iter().map(|player| player.info.clone()).collect(),
+        };
+        info!(self.logger, "game info";
+            "game_info" => slog::Serde(game_info.clone()),
+        );
+        for player in self.players.values_mut() {
+            player.send_game_info(&game_info);
+            let client_id = player.handle.id();
+            if self.forfeited.contains(&client_id)
+                || self.disqualified.contains_key(&client_id)
+            {
+                self.state.forfeit(player.id);
+            }
+        }
+        // strikes do not carry over to the next round
+        self.referee = Referee::new(self.conf.strikes.clone());
+
         self.log_state();
-        self.prompt_players();
+        // everyone might have forfeited
+        if self.state.is_finished() {
+            self.finish_game();
+        } else {
+            self.next_turn();
+        }
+    }
+
+    /// Move every player to the next seat, and reset the game for a rematch.
+    fn swap_seats(&mut self) {
+        let num_players = self.state.players.len();
+        let players: Vec<Player> = self.players.drain()
+            .map(|(_, player)| player)
+            .collect();
+        for mut player in players {
+            player.id = PlayerId::new((player.id.as_usize() + 1) % num_players);
+            self.client_player.insert(player.handle.id(), player.id);
+            self.players.insert(player.id, player);
+        }
+        self.state = self.initial_state.clone();
     }
 
     /// Advance the game by one turn.
@@ -375,10 +637,24 @@ impl PwController {
         if self.state.is_finished() {
             self.finish_game();
         } else {
+            self.next_turn();
+        }
+    }
+
+    /// Prompt the players for the next turn, unless the match is paused.
+    fn next_turn(&mut self) {
+        if !self.paused {
             self.prompt_players();
         }
     }
 
+    /// Whether the game is held between two turns, because it is paused.
+    fn is_held(&self) -> bool {
+        self.connect_timeout.is_none()
+            && !self.is_finished()
+            && self.waiting_for.is_empty()
+    }
+
     fn outcome(&self) -> Option<Vec<PlayerId>> {
         if self.state.is_finished() {
             Some(self.state.living_players())
@@ -388,10 +664,15 @@ impl PwController {
     }
 
     fn log_state(&mut self) {
-        // TODO: add turn number
-        info!(self.logger, "step"; serialize(&self.state));
+        info!(self.logger, "step";
+            "state" => slog::Serde(serialize(&self.state)),
+        );
         let serialized_state = serialize(&self.state);
-        let message = proto::ControlMessage::GameState(serialized_state);
+        self.notify(proto::ControlMessage::GameState(serialized_state));
+    }
+
+    /// Send a message to the control client.
+    fn notify(&mut self, message: proto::ControlMessage) {
         let serialized = serde_json::to_vec(&message).unwrap();
         self.ctrl_handle.send(serialized);
     }
@@ -403,23 +684,82 @@ impl PwController {
         // does not have to borrow self (which would create a lifetime conflict)
         let state = &self.state;
         let waiting_for = &mut self.waiting_for;
+        let last_round = self.rematches == 0;
+        let visibility = self.conf.expedition_visibility;
 
         self.players.retain(|player_id, player| {
             if state.players[player_id.as_usize()].alive {
                 waiting_for.insert(player.id);
-                player.prompt(state, deadline);
+                player.prompt(state, visibility, deadline);
                 // keep this player in the game
                 return true;
-            } else {
+            } else if last_round {
                 player.send_final_state(state);
                 // this player is dead, kick him!
                 return false;
+            } else {
+                // sit out the rest of this round
+                return true;
             }
         });
     }
 
+    /// Take a player out of the game for misbehaving.
+    fn disqualify(&mut self, player_id: PlayerId, reason: String) {
+        info!(self.logger, "player disqualified";
+            "player_id" => player_id.as_usize(),
+            "reason" => &reason,
+        );
+        self.state.forfeit(player_id);
+
+        let client_id = self.players[&player_id].handle.id();
+        let ClientId(client_num) = client_id;
+        self.notify(proto::ControlMessage::PlayerDisqualified(
+            proto::Disqualification {
+                player_id: client_num,
+                reason: reason.clone(),
+            }
+        ));
+        self.disqualified.insert(client_id, reason);
+    }
+
+    fn match_result(&self) -> proto::MatchResult {
+        let mut survivors: Vec<u64> = self.state.living_players().iter()
+            .map(|player_id| {
+                let ClientId(client_num) = self.players[player_id].handle.id();
+                client_num
+            })
+            .collect();
+        survivors.sort();
+
+        let mut disqualified: Vec<proto::Disqualification> = self.disqualified
+            .iter()
+            .map(|(&ClientId(client_num), reason)| proto::Disqualification {
+                player_id: client_num,
+                reason: reason.clone(),
+            })
+            .collect();
+        disqualified.sort_by_key(|disqualification| disqualification.player_id);
+
+        proto::MatchResult {
+            survivors,
+            disqualified,
+        }
+    }
+
     // TODO: ewwwww dup
     fn finish_game(&mut self) {
+        let result = self.match_result();
+        info!(self.logger, "match result"; result.clone());
+        self.notify(proto::ControlMessage::MatchResult(result));
+
+        if self.rematches > 0 {
+            self.rematches -= 1;
+            self.swap_seats();
+            self.start_game();
+            return;
+        }
+
         let state = &self.state;
 
         self.players.retain(|_player_id, player| {
@@ -429,117 +769,324 @@ impl PwController {
         });
     }
 
-    fn execute_messages(&mut self, mut msgs: HashMap<PlayerId, ResponseValue>) {
-        for (player_id, result) in msgs.drain() {
+    fn handle_message(&mut self, message_id: MessageId, content: Vec<u8>) {
+        let payload = match decode_lobby_message(content) {
+            Ok(payload) => payload,
+            Err(error) => {
+                let response = lobby_message::ErrorResponse {
+                    error: Some(error),
+                };
+                self.ctrl_handle.respond(message_id, encode_message(&response));
+                return;
+            }
+        };
+        let already_started = || lobby_error(
+            LobbyErrorKind::AlreadyStarted,
+            "the match has already started".to_string(),
+        );
+        let result = match payload {
+            lobby_message::Payload::AddPlayer(_) => {
+                let response = lobby_message::AddPlayerResponse {
+                    response: Some(add_player_response::Response::Error(
+                        already_started()
+                    )),
+                };
+                self.ctrl_handle.respond(message_id, encode_message(&response));
+                return;
+            }
+            lobby_message::Payload::RemovePlayer(_) => {
+                let response = lobby_message::RemovePlayerResponse {
+                    response: Some(remove_player_response::Response::Error(
+                        already_started()
+                    )),
+                };
+                self.ctrl_handle.respond(message_id, encode_message(&response));
+                return;
+            }
+            lobby_message::Payload::StartGame(_) => {
+                let response = lobby_message::StartGameResponse {
+                    response: Some(start_game_response::Response::Error(
+                        already_started()
+                    )),
+                };
+                self.ctrl_handle.respond(message_id, encode_message(&response));
+                return;
+            }
+            lobby_message::Payload::Pause(_) => self.pause(),
+            lobby_message::Payload::Resume(_) => self.resume(),
+            lobby_message::Payload::Step(_) => self.single_step(),
+            lobby_message::Payload::Abort(request) => self.abort(request.reason),
+        };
+        let response = control_result(result);
+        self.ctrl_handle.respond(message_id, encode_message(&response));
+    }
+
+    /// Stop prompting players once the current turn is done.
+    fn pause(&mut self) -> Result<(), LobbyError> {
+        if !self.paused {
+            info!(self.logger, "match paused");
+            self.paused = true;
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), LobbyError> {
+        if !self.paused {
+            return Err(lobby_error(
+                LobbyErrorKind::NotPaused,
+                "the match is not paused".to_string(),
+            ));
+        }
+        info!(self.logger, "match resumed");
+        self.paused = false;
+        if self.is_held() {
+            self.prompt_players();
+        }
+        Ok(())
+    }
+
+    /// Play a single turn of a paused match.
+    fn single_step(&mut self) -> Result<(), LobbyError> {
+        if !self.paused {
+            return Err(lobby_error(
+                LobbyErrorKind::NotPaused,
+                "only a paused match can be stepped".to_string(),
+            ));
+        }
+        // when a turn is still being played, that turn is the step
+        if self.is_held() {
+            self.prompt_players();
+        }
+        Ok(())
+    }
+
+    /// End the match right away; players get the state as it is now.
+    fn abort(&mut self, reason: String) -> Result<(), LobbyError> {
+        info!(self.logger, "match aborted"; "reason" => &reason);
+        self.notify(proto::ControlMessage::MatchAborted { reason });
+
+        let state = &self.state;
+        self.players.retain(|_player_id, player| {
+            player.send_final_state(state);
+            return false;
+        });
+        self.connect_timeout = None;
+        self.cancelled = true;
+        Ok(())
+    }
+
+    fn execute_messages(&mut self, msgs: HashMap<PlayerId, ResponseValue>) {
+        // Handle players in a fixed order, so that the log does not depend on
+        // hashmap iteration order.
+        let mut responses: Vec<_> = msgs.into_iter().collect();
+        responses.sort_by_key(|&(player_id, _)| player_id.as_usize());
+
+        for &(player_id, ref result) in responses.iter() {
             // log received message
             // TODO: this should probably happen in the lock, so that
             //       we have a correct timestamp.
-            match &result {
+            match result {
                 &Ok(ref message) => {
-                    let content = match String::from_utf8(message.clone()) {
-                        Ok(content) => content,
-                        Err(_err) => "invalid utf-8".to_string(),
-                    };
                     info!(self.logger, "message received";
-                        player_id,
-                        "content" => content,
+                        "player_id" => player_id.as_usize(),
+                        "content" => logged_content(message),
                     );
                 },
                 &Err(ResponseError::Timeout) => {
-                    info!(self.logger, "timeout"; player_id);
+                    info!(self.logger, "timeout";
+                        "player_id" => player_id.as_usize(),
+                    );
                 }
             }
+        }
 
-            let player_action = self.execute_action(player_id, result);
+        let results = execute_actions(
+            &mut self.state,
+            &self.planet_map,
+            self.conf.overdraw,
+            responses,
+        );
+        let mut disqualified = Vec::new();
+        for (player_id, player_action, debug) in results {
+            if let Some(debug) = debug {
+                // only recorded in the log; never sent to other players.
+                info!(self.logger, "player debug";
+                    "player_id" => player_id.as_usize(),
+                    "debug" => slog::Serde(debug),
+                );
+            }
+            if let Some(reason) = self.referee.judge(player_id, &player_action) {
+                disqualified.push((player_id, reason));
+            }
             self.players.get_mut(&player_id).unwrap()
                 .send_action(player_action);
         }
+        for (player_id, reason) in disqualified {
+            self.disqualify(player_id, reason);
+        }
     }
 
-    fn execute_action(&mut self, player_id: PlayerId, response: ResponseValue)
-        -> PlayerAction
-    {
-        // TODO: it would be cool if this could be done with error_chain.
-
-        let message = match response {
-            Err(ResponseError::Timeout) => return PlayerAction::Timeout,
-            Ok(message) => message,
-        };
-
-        let action: proto::Action = match serde_json::from_slice(&message) {
-            Err(err) => return PlayerAction::ParseError(err.to_string()),
-            Ok(action) => action,
-        };
+    fn handle_event(&mut self, event: Event) {
+        let from_player = self.client_player.contains_key(&event.client_id);
+        match event.content {
+            EventContent::Connected if from_player => {
+                let ClientId(client_num) = event.client_id;
+                self.notify(proto::ControlMessage::PlayerConnected {
+                    player_id: client_num,
+                });
+                self.connected.insert(event.client_id);
+                if self.connect_timeout.is_some() {
+                    self.start_when_connected();
+                }
+            },
+            EventContent::Disconnected if from_player => {
+                let ClientId(client_num) = event.client_id;
+                self.notify(proto::ControlMessage::PlayerDisconnected {
+                    player_id: client_num,
+                });
+                self.connected.remove(&event.client_id);
+            },
+            EventContent::Connected | EventContent::Disconnected => {},
+            EventContent::Message { message_id, data } => {
+                // players have no business sending us messages
+                if event.client_id == self.ctrl_handle.id() {
+                    self.handle_message(message_id, data);
+                }
+            },
+            EventContent::Response { value, .. } => {
+                // we only send requests to players
+                let &player_id = self.client_player
+                    .get(&event.client_id).unwrap();
+                self.commands.insert(player_id, value);
+                self.waiting_for.remove(&player_id);
 
-        let commands = action.commands.into_iter().map(|command| {
-            match self.parse_command(player_id, &command) {
-                Ok(dispatch) => {
-                    self.state.dispatch(&dispatch);
-                    PlayerCommand {
-                        command,
-                        error: None,
-                    }
-                },
-                Err(error) => {
-                    PlayerCommand {
-                        command,
-                        error: Some(error),
-                    }
+                if self.waiting_for.is_empty() {
+                    let commands = mem::replace(&mut self.commands, HashMap::new());
+                    self.step(commands);
                 }
             }
-        }).collect();
-
-        return PlayerAction::Commands(commands);
+        }
     }
+}
 
-    fn parse_command(&self, player_id: PlayerId, mv: &proto::Command)
-                     -> Result<Dispatch, CommandError>
-    {
-        let origin_id = *self.planet_map
-            .get(&mv.origin)
-            .ok_or(CommandError::OriginDoesNotExist)?;
+fn decode_lobby_message(content: Vec<u8>)
+    -> Result<lobby_message::Payload, LobbyError>
+{
+    let message = LobbyMessage::decode(content).map_err(|err| lobby_error(
+        LobbyErrorKind::InvalidMessage,
+        format!("could not decode lobby message: {}", err),
+    ))?;
+    return message.payload.ok_or_else(|| lobby_error(
+        LobbyErrorKind::InvalidMessage,
+        "lobby message has no known payload".to_string(),
+    ));
+}
 
-        let target_id = *self.planet_map
-            .get(&mv.destination)
-            .ok_or(CommandError::DestinationDoesNotExist)?;
+fn control_result(result: Result<(), LobbyError>)
+    -> lobby_message::ControlResponse
+{
+    let response = match result {
+        Ok(()) => control_response::Response::Success(
+            lobby_message::ControlSuccess {}
+        ),
+        Err(error) => control_response::Response::Error(error),
+    };
+    return lobby_message::ControlResponse {
+        response: Some(response),
+    };
+}
 
-        if self.state.planets[origin_id].owner() != Some(player_id) {
-            return Err(CommandError::OriginNotOwned);
-        }
+/// Read the match config and set up the game, so that a broken config or map
+/// is refused before the match starts. `seats` holds the seat every player
+/// asked for.
+fn create_game(payload: &[u8], seats: &[u64], map_pool: Option<&Path>)
+    -> Result<(Config, PlanetWars), LobbyError>
+{
+    let num_players = seats.len();
+    if num_players == 0 {
+        return Err(lobby_error(
+            LobbyErrorKind::NoPlayers,
+            "no players have been added".to_string(),
+        ));
+    }
 
-        if self.state.planets[origin_id].ship_count() < mv.ship_count {
-            return Err(CommandError::NotEnoughShips);
+    let mut taken = HashSet::new();
+    for &seat in seats.iter().filter(|&&seat| seat > 0) {
+        if seat as usize > num_players {
+            return Err(lobby_error(
+                LobbyErrorKind::InvalidSeat,
+                format!("there is no seat {} for {} players", seat, num_players),
+            ));
         }
-
-        if mv.ship_count == 0 {
-            return Err(CommandError::ZeroShipMove);
+        if !taken.insert(seat) {
+            return Err(lobby_error(
+                LobbyErrorKind::InvalidSeat,
+                format!("seat {} was requested twice", seat),
+            ));
         }
+    }
 
-        Ok(Dispatch {
-            origin: origin_id,
-            target: target_id,
-            ship_count: mv.ship_count,
+    let mut conf: Config = serde_json::from_slice(payload)
+        .map_err(|err| lobby_error(
+            LobbyErrorKind::InvalidConfig,
+            format!("could not parse game data: {}", err),
+        ))?;
+
+    conf.validate(num_players).map_err(|msg| lobby_error(
+        LobbyErrorKind::InvalidConfig,
+        msg,
+    ))?;
+
+    // all randomness in the match is derived from this seed, so that
+    // the match can be reproduced from the log.
+    let seed = conf.seed.unwrap_or_else(rand::random);
+    conf.seed = Some(seed);
+
+    let state = conf.create_game(num_players, map_pool)
+        .map_err(|err| lobby_error(
+            LobbyErrorKind::InvalidMap,
+            err.to_string(),
+        ))?;
+
+    let seats = state.players.iter()
+        .take_while(|player| {
+            state.planets.iter().any(|p| p.owner() == Some(player.id))
         })
+        .count();
+    if seats < num_players {
+        return Err(lobby_error(
+            LobbyErrorKind::TooManyPlayers,
+            format!(
+                "the map has start planets for {} players, but {} joined",
+                seats, num_players,
+            ),
+        ));
     }
 
-    fn handle_event(&mut self, event: Event) {
-        match event.content {
-            EventContent::Connected => {},
-            EventContent::Disconnected => {},
-            EventContent::Message { .. } => {},
-            EventContent::Response { value, .. } => {
-                // we only send requests to players
-                let &player_id = self.client_player
-                    .get(&event.client_id).unwrap();
-                self.commands.insert(player_id, value);
-                self.waiting_for.remove(&player_id);
-            }
-        }
+    return Ok((conf, state));
+}
+
+/// Give every player its requested seat, and hand out the free seats to the
+/// other players in order. Seats count from 1, and 0 means any seat.
+fn assign_seats(requested: &[u64]) -> Vec<PlayerId> {
+    let mut taken = vec![false; requested.len()];
+    for &seat in requested.iter().filter(|&&seat| seat > 0) {
+        taken[seat as usize - 1] = true;
+    }
 
-        if self.waiting_for.is_empty() {
-            let commands = mem::replace(&mut self.commands, HashMap::new());
-            self.step(commands);
+    let mut free = (0..requested.len()).filter(|&id| !taken[id]);
+    return requested.iter().map(|&seat| {
+        match seat {
+            0 => PlayerId::new(free.next().unwrap()),
+            seat => PlayerId::new(seat as usize - 1),
         }
+    }).collect();
+}
+
+fn lobby_error(kind: LobbyErrorKind, message: String) -> LobbyError {
+    LobbyError {
+        kind: kind as i32,
+        message,
     }
 }
 
@@ -554,4 +1101,142 @@ fn encode_message<M>(message: &M) -> Vec<u8>
     // space.
     message.encode(&mut bytes).unwrap();
     return bytes;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "map": { "planets": [
+            { "name": "a", "x": 0, "y": 0, "owner": 1, "ship_count": 5 },
+            { "name": "b", "x": 4, "y": 0, "owner": 2, "ship_count": 5 }
+        ]},
+        "max_turns": 10
+    }"#;
+
+    fn seated_error_kind(payload: &str, seats: &[u64]) -> Option<LobbyErrorKind> {
+        create_game(payload.as_bytes(), seats, None).err()
+            .map(|error| LobbyErrorKind::from_i32(error.kind).unwrap())
+    }
+
+    fn error_kind(payload: &str, num_players: usize) -> Option<LobbyErrorKind> {
+        seated_error_kind(payload, &vec![0; num_players])
+    }
+
+    #[test]
+    fn valid_config() {
+        assert_eq!(error_kind(CONFIG, 2), None);
+        // leftover start planets are fine
+        assert_eq!(error_kind(CONFIG, 1), None);
+    }
+
+    #[test]
+    fn refused_configs() {
+        assert_eq!(error_kind(CONFIG, 0), Some(LobbyErrorKind::NoPlayers));
+        assert_eq!(error_kind(CONFIG, 3), Some(LobbyErrorKind::TooManyPlayers));
+        assert_eq!(
+            error_kind(r#"{ "map_file": "map.json" }"#, 2),
+            Some(LobbyErrorKind::InvalidConfig),
+        );
+        assert_eq!(
+            error_kind(r#"{ "map_file": "no/map.json", "max_turns": 1 }"#, 2),
+            Some(LobbyErrorKind::InvalidMap),
+        );
+        assert_eq!(
+            error_kind(r#"{
+                "generate_map": { "planet_amount": { "min": 5, "max": 5 } },
+                "max_turns": 10
+            }"#, 2),
+            Some(LobbyErrorKind::InvalidConfig),
+        );
+        assert_eq!(
+            error_kind(r#"{
+                "generate_map": { "min_spacing": 1000, "symmetry": "rotational" },
+                "max_turns": 10
+            }"#, 2),
+            Some(LobbyErrorKind::InvalidMap),
+        );
+    }
+
+    #[test]
+    fn refused_seats() {
+        assert_eq!(seated_error_kind(CONFIG, &[2, 0]), None);
+        assert_eq!(
+            seated_error_kind(CONFIG, &[2, 2]),
+            Some(LobbyErrorKind::InvalidSeat),
+        );
+        assert_eq!(
+            seated_error_kind(CONFIG, &[0, 3]),
+            Some(LobbyErrorKind::InvalidSeat),
+        );
+    }
+
+    #[test]
+    fn lobby_message_decoding() {
+        let decode = |message: &LobbyMessage| {
+            decode_lobby_message(encode_message(message))
+        };
+
+        let pause = LobbyMessage {
+            payload: Some(lobby_message::Payload::Pause(
+                lobby_message::PauseRequest {}
+            )),
+        };
+        match decode(&pause) {
+            Ok(lobby_message::Payload::Pause(_)) => {},
+            _ => panic!("pause request not decoded"),
+        }
+
+        let empty = LobbyMessage { payload: None };
+        let error = decode(&empty).err().unwrap();
+        assert_eq!(
+            LobbyErrorKind::from_i32(error.kind),
+            Some(LobbyErrorKind::InvalidMessage),
+        );
+        assert!(decode_lobby_message(vec![0xff]).is_err());
+    }
+
+    #[test]
+    fn error_responses_read_as_errors() {
+        let response = lobby_message::ErrorResponse {
+            error: Some(lobby_error(
+                LobbyErrorKind::InvalidMessage,
+                "could not decode lobby message".to_string(),
+            )),
+        };
+        let bytes = encode_message(&response);
+
+        let add = lobby_message::AddPlayerResponse::decode(&bytes).unwrap();
+        match add.response {
+            Some(add_player_response::Response::Error(_)) => {},
+            other => panic!("read as {:?}", other),
+        }
+        let remove = lobby_message::RemovePlayerResponse::decode(&bytes)
+            .unwrap();
+        match remove.response {
+            Some(remove_player_response::Response::Error(_)) => {},
+            other => panic!("read as {:?}", other),
+        }
+        let start = lobby_message::StartGameResponse::decode(&bytes).unwrap();
+        match start.response {
+            Some(start_game_response::Response::Error(_)) => {},
+            other => panic!("read as {:?}", other),
+        }
+        let control = lobby_message::ControlResponse::decode(&bytes).unwrap();
+        match control.response {
+            Some(control_response::Response::Error(_)) => {},
+            other => panic!("read as {:?}", other),
+        }
+    }
+
+    #[test]
+    fn seat_assignment() {
+        let ids = |requested: &[u64]| -> Vec<usize> {
+            assign_seats(requested).iter().map(|id| id.as_usize()).collect()
+        };
+        assert_eq!(ids(&[0, 0, 0]), vec![0, 1, 2]);
+        assert_eq!(ids(&[0, 1, 0]), vec![1, 0, 2]);
+        assert_eq!(ids(&[3, 0, 2]), vec![2, 0, 1]);
+    }
+}
@@ -1,89 +1,39 @@
 use slog;
 use erased_serde;
 
+/// Maps use the format shared with the map tools.
+pub use map_model::Map;
+/// The messages bots exchange with the server are defined with the game
+/// rules, so bots can use them too.
+pub use planetwars_rules::protocol::{
+    State,
+    Planet,
+    Expedition,
+    TravelTimes,
+    Action,
+    DebugInfo,
+    Command,
+    CommandError,
+    PlayerCommand,
+    PlayerAction,
+    GameInfo,
+    PlayerInfo,
+    ServerMessage,
+};
+
+/// How a round of a match ended. Like in the control messages, players are
+/// identified by their client id.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Expedition {
-    pub id: u64,
-    pub ship_count: u64,
-    pub origin: String,
-    pub destination: String,
-    pub owner: u64,
-    pub turns_remaining: u64,
+pub struct MatchResult {
+    /// The players that were still in the game at the end.
+    pub survivors: Vec<u64>,
+    pub disqualified: Vec<Disqualification>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Planet {
-    pub ship_count: u64,
-    pub x: f64,
-    pub y: f64,
-    pub owner: Option<u64>,
-    pub name: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Action {
-    #[serde(rename = "moves")]
-    pub commands: Vec<Command>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Command {
-    pub origin: String,
-    pub destination: String,
-    pub ship_count: u64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Map {
-    pub planets: Vec<Planet>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct State {
-    pub planets: Vec<Planet>,
-    pub expeditions: Vec<Expedition>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GameInfo {
-    pub players: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum CommandError {
-    NotEnoughShips,
-    OriginNotOwned,
-    ZeroShipMove,
-    OriginDoesNotExist,
-    DestinationDoesNotExist,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PlayerCommand {
-    pub command: Command,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<CommandError>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-#[serde(tag = "type", content = "value")]
-pub enum PlayerAction {
-    Timeout,
-    ParseError(String),
-    Commands(Vec<PlayerCommand>),
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-#[serde(tag = "type", content = "content")]
-pub enum ServerMessage {
-    /// Game state in current turn
-    GameState(State),
-    /// The action that was performed
-    PlayerAction(PlayerAction),
-    /// The game is over, and this is the concluding state.
-    FinalState(State),
+pub struct Disqualification {
+    pub player_id: u64,
+    pub reason: String,
 }
 
 // lobby messages
@@ -98,6 +48,23 @@ pub enum ControlMessage {
         player_id: u64,
     },
     GameState(State),
+    /// These players did not connect in time, and lost. Like in the other
+    /// control messages, players are identified by their client id.
+    PlayersForfeited {
+        player_ids: Vec<u64>,
+    },
+    /// The match was called off before it started.
+    MatchCancelled {
+        reason: String,
+    },
+    /// The control client stopped the match.
+    MatchAborted {
+        reason: String,
+    },
+    /// The player misbehaved too often, and was taken out of the match.
+    PlayerDisqualified(Disqualification),
+    /// Sent at the end of every round.
+    MatchResult(MatchResult),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,38 +74,7 @@ pub enum LobbyCommand {
     StartMatch,
 }
 
-impl slog::Value for Command {
-    fn serialize(&self,
-                 _record: &slog::Record,
-                 key: slog::Key,
-                 serializer: &mut slog::Serializer)
-                 -> slog::Result
-    {
-        serializer.emit_serde(key, self)
-    }
-}
-
-impl slog::KV for Command {
-    fn serialize(&self,
-                 _record: &slog::Record,
-                 serializer: &mut slog::Serializer)
-                 -> slog::Result
-    {
-        serializer.emit_serde("command", self)
-    }
-}
-
-impl slog::SerdeValue for Command {
-    fn as_serde(&self) -> &erased_serde::Serialize {
-        self
-    }
-
-    fn to_sendable(&self) -> Box<slog::SerdeValue + Send + 'static> {
-        Box::new(self.clone())
-    }
-}
-
-impl slog::Value for State {
+impl slog::Value for MatchResult {
     fn serialize(&self,
                  _record: &slog::Record,
                  key: slog::Key,
@@ -149,17 +85,17 @@ impl slog::Value for State {
     }
 }
 
-impl slog::KV for State {
+impl slog::KV for MatchResult {
     fn serialize(&self,
                  _record: &slog::Record,
                  serializer: &mut slog::Serializer)
                  -> slog::Result
     {
-        serializer.emit_serde("state", self)
+        serializer.emit_serde("match_result", self)
     }
 }
 
-impl slog::SerdeValue for State {
+impl slog::SerdeValue for MatchResult {
     fn as_serde(&self) -> &erased_serde::Serialize {
         self
     }
@@ -168,25 +104,3 @@ impl slog::SerdeValue for State {
         Box::new(self.clone())
     }
 }
-
-
-impl slog::Value for GameInfo {
-    fn serialize(&self,
-                 _record: &slog::Record,
-                 key: slog::Key,
-                 serializer: &mut slog::Serializer)
-                 -> slog::Result
-    {
-        serializer.emit_serde(key, self)
-    }
-}
-
-impl slog::SerdeValue for GameInfo {
-    fn as_serde(&self) -> &erased_serde::Serialize {
-        self
-    }
-
-    fn to_sendable(&self) -> Box<slog::SerdeValue + Send + 'static> {
-        Box::new(self.clone())
-    }
-}
\ No newline at end of file
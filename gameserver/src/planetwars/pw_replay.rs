@@ -0,0 +1,346 @@
+use std::io::{self, BufRead};
+
+use serde_json::{self, Value};
+use slog;
+use planetwars_rules::{PlanetWars, PlayerId};
+
+use utils::client_handler::{ResponseValue, ResponseError};
+
+use super::Config;
+use super::pw_commands::{
+    execute_actions,
+    logged_content,
+    planet_map,
+    PlanetMap,
+};
+use super::pw_strikes::Referee;
+use super::pw_serializer::{serialize, serialize_rotated};
+use super::pw_protocol::{self as proto, DebugInfo, PlayerAction};
+
+/// The response a player sent in, as recorded in a match log.
+/// `None` marks a timeout.
+pub type LoggedResponse = (PlayerId, Option<String>);
+
+/// The information in a match log that is required to re-simulate a match.
+pub struct MatchLog {
+    pub config: Config,
+    pub num_players: usize,
+    /// The game state at the start of every turn.
+    pub states: Vec<proto::State>,
+    /// For every turn, the responses of the players in the order they were
+    /// executed.
+    pub turns: Vec<Vec<LoggedResponse>>,
+    /// For every turn, the debug info the players sent along.
+    pub debug: Vec<Vec<(PlayerId, DebugInfo)>>,
+}
+
+impl MatchLog {
+    /// Read a log as written by the game server.
+    pub fn read<R: BufRead>(reader: R) -> io::Result<MatchLog> {
+        MatchLog::read_round(reader, 0)
+    }
+
+    /// Read one round of a log. Matches with swapped seats play a round for
+    /// every seat rotation; they are numbered from 0.
+    pub fn read_round<R: BufRead>(reader: R, round: usize)
+        -> io::Result<MatchLog>
+    {
+        let mut config = None;
+        let mut num_players = 0;
+        let mut states = Vec::new();
+        let mut turns: Vec<Vec<LoggedResponse>> = Vec::new();
+        let mut debug: Vec<Vec<(PlayerId, DebugInfo)>> = Vec::new();
+        // every round starts with its config
+        let mut rounds_seen = 0;
+
+        for line in reader.lines() {
+            let record: Value = serde_json::from_str(&line?)?;
+            let msg = record["msg"].as_str();
+            if msg == Some("match config") {
+                rounds_seen += 1;
+            }
+            if rounds_seen != round + 1 {
+                continue;
+            }
+            match msg {
+                Some("match config") => {
+                    config = Some(serde_json::from_value(
+                        record["config"].clone()
+                    )?);
+                }
+                Some("player assigned") => {
+                    num_players += 1;
+                }
+                Some("step") => {
+                    states.push(serde_json::from_value(
+                        record["state"].clone()
+                    )?);
+                    turns.push(Vec::new());
+                    debug.push(Vec::new());
+                }
+                Some("message received") => {
+                    let content = record["content"].as_str()
+                        .map(|content| content.to_string());
+                    let response = (logged_player_id(&record)?, content);
+                    current_turn(&mut turns)?.push(response);
+                }
+                Some("timeout") => {
+                    let response = (logged_player_id(&record)?, None);
+                    current_turn(&mut turns)?.push(response);
+                }
+                Some("player debug") => {
+                    let info = serde_json::from_value(
+                        record["debug"].clone()
+                    )?;
+                    let entry = (logged_player_id(&record)?, info);
+                    current_turn(&mut debug)?.push(entry);
+                }
+                _ => {}
+            }
+        }
+
+        if rounds_seen > 0 && rounds_seen <= round {
+            return Err(invalid_data("log contains no such round"));
+        }
+        let config = config.ok_or_else(|| invalid_data(
+            "log contains no match config; was it written by an older server?"
+        ))?;
+        if states.is_empty() {
+            return Err(invalid_data("log contains no game states"));
+        }
+
+        return Ok(MatchLog {
+            config,
+            num_players,
+            states,
+            turns,
+            debug,
+        });
+    }
+
+    /// The debug info given player sent along, with the turn it was sent in.
+    /// The log holds the debug info of every player; this is the part that
+    /// can be handed to the player's owner.
+    pub fn player_debug(&self, player_id: PlayerId) -> Vec<(u64, &DebugInfo)> {
+        self.debug.iter().enumerate().flat_map(|(turn_num, entries)| {
+            entries.iter()
+                .filter(move |&&(id, _)| id == player_id)
+                .map(move |(_, info)| (turn_num as u64, info))
+        }).collect()
+    }
+}
+
+fn logged_player_id(record: &Value) -> io::Result<PlayerId> {
+    record["player_id"].as_u64()
+        .map(|id| PlayerId::new(id as usize))
+        .ok_or_else(|| invalid_data("record is missing a player id"))
+}
+
+fn current_turn<T>(turns: &mut [Vec<T>]) -> io::Result<&mut Vec<T>> {
+    turns.last_mut()
+        .ok_or_else(|| invalid_data("player response before first step"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Re-simulates a logged match, optionally letting a local bot take over a
+/// player from some turn on.
+pub struct Replay {
+    log: MatchLog,
+    state: PlanetWars,
+    planet_map: PlanetMap,
+    referee: Referee,
+    logger: slog::Logger,
+}
+
+impl Replay {
+    /// Start a replay at the first turn of given log. Every turn played will
+    /// be written to `logger` in the same format the server uses.
+    pub fn new(log: MatchLog, logger: slog::Logger) -> Self {
+        let state = log.config.restore_game(log.num_players, &log.states[0], 0);
+        let planet_map = planet_map(&state);
+        let referee = Referee::new(log.config.strikes.clone());
+        info!(logger, "match config"; log.config.clone());
+        info!(logger, "step"; "state" => slog::Serde(serialize(&state)));
+
+        Replay {
+            log,
+            state,
+            planet_map,
+            referee,
+            logger,
+        }
+    }
+
+    pub fn turn_num(&self) -> u64 {
+        self.state.turn_num
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.is_finished()
+    }
+
+    /// The current game state, in protocol format.
+    pub fn serialized_state(&self) -> proto::State {
+        serialize(&self.state)
+    }
+
+    /// Replay the logged turns until given turn is reached.
+    /// Returns the turns at which the re-simulated state did not match the
+    /// logged state.
+    pub fn seek(&mut self, turn_num: u64) -> Vec<u64> {
+        let mut divergences = Vec::new();
+        while self.turn_num() < turn_num && !self.is_finished() {
+            self.play_logged_turn();
+            let turn = self.turn_num() as usize;
+            if let Some(logged) = self.log.states.get(turn) {
+                let logged = serde_json::to_value(logged).unwrap();
+                let actual = serde_json::to_value(&self.serialized_state())
+                    .unwrap();
+                if logged != actual {
+                    divergences.push(self.turn_num());
+                }
+            }
+        }
+        return divergences;
+    }
+
+    /// Play a turn using only logged responses. Players for which no response
+    /// was logged time out.
+    pub fn play_logged_turn(&mut self) -> Vec<(PlayerId, PlayerAction)> {
+        let responses = self.logged_responses();
+        self.play_turn(responses)
+    }
+
+    /// Play a turn in which `player_id` is played by a local bot. The bot
+    /// receives the serialized state as the player would have received it,
+    /// and returns its response, or `None` when it timed out.
+    pub fn play_substituted_turn<F>(&mut self, player_id: PlayerId, mut bot: F)
+        -> Vec<(PlayerId, PlayerAction)>
+        where F: FnMut(String) -> Option<String>
+    {
+        let mut responses: Vec<LoggedResponse> = self.logged_responses()
+            .into_iter()
+            .filter(|&(id, _)| id != player_id)
+            .collect();
+
+        if self.state.players[player_id.as_usize()].alive {
+            let offset = self.state.players.len() - player_id.as_usize();
+            let visibility = self.log.config.expedition_visibility;
+            let state = serialize_rotated(&self.state, offset, visibility);
+            let request = serde_json::to_string(&state).unwrap();
+            responses.push((player_id, bot(request)));
+        }
+
+        self.play_turn(responses)
+    }
+
+    fn logged_responses(&self) -> Vec<LoggedResponse> {
+        let turn = self.turn_num() as usize;
+        let logged = self.log.turns.get(turn).cloned().unwrap_or_default();
+        let state = &self.state;
+        // Players that are alive but have no logged response (because the
+        // replay diverged from the log) are considered to time out.
+        let mut responses = logged;
+        for player in state.players.iter().filter(|p| p.alive) {
+            if !responses.iter().any(|&(id, _)| id == player.id) {
+                responses.push((player.id, None));
+            }
+        }
+        return responses;
+    }
+
+    /// Advance the game by one turn, the way the controller does.
+    fn play_turn(&mut self, mut responses: Vec<LoggedResponse>)
+        -> Vec<(PlayerId, PlayerAction)>
+    {
+        self.state.repopulate();
+
+        responses.sort_by_key(|&(player_id, _)| player_id.as_usize());
+        let values = responses.into_iter().map(|(player_id, content)| {
+            let value: ResponseValue = match content {
+                Some(content) => {
+                    info!(self.logger, "message received";
+                        "player_id" => player_id.as_usize(),
+                        "content" => logged_content(content.as_bytes()),
+                    );
+                    Ok(content.into_bytes())
+                }
+                None => {
+                    info!(self.logger, "timeout";
+                        "player_id" => player_id.as_usize(),
+                    );
+                    Err(ResponseError::Timeout)
+                }
+            };
+            (player_id, value)
+        }).collect();
+
+        let results = execute_actions(
+            &mut self.state,
+            &self.planet_map,
+            self.log.config.overdraw,
+            values,
+        );
+        let mut actions = Vec::new();
+        let mut disqualified = Vec::new();
+        for (player_id, action, debug) in results {
+            if let Some(debug) = debug {
+                info!(self.logger, "player debug";
+                    "player_id" => player_id.as_usize(),
+                    "debug" => slog::Serde(debug),
+                );
+            }
+            if let Some(reason) = self.referee.judge(player_id, &action) {
+                disqualified.push((player_id, reason));
+            }
+            actions.push((player_id, action));
+        }
+        for (player_id, reason) in disqualified {
+            info!(self.logger, "player disqualified";
+                "player_id" => player_id.as_usize(),
+                "reason" => reason,
+            );
+            self.state.forfeit(player_id);
+        }
+
+        self.state.step();
+        info!(self.logger, "step";
+            "state" => slog::Serde(serialize(&self.state)),
+        );
+        return actions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = r#"
+{"msg":"match config","config":{"map_file":"","max_turns":10}}
+{"msg":"player assigned","player_id":0}
+{"msg":"player assigned","player_id":1}
+{"msg":"step","state":{"planets":[],"expeditions":[]}}
+{"msg":"player debug","player_id":0,"debug":{"messages":["mine"]}}
+{"msg":"player debug","player_id":1,"debug":{"messages":["theirs"]}}
+{"msg":"step","state":{"planets":[],"expeditions":[]}}
+{"msg":"player debug","player_id":0,"debug":{"messages":["mine again"]}}
+"#;
+
+    #[test]
+    fn debug_info_is_read_per_player() {
+        let log = MatchLog::read(LOG.trim().as_bytes()).unwrap();
+        let messages = |player_num| {
+            log.player_debug(PlayerId::new(player_num)).into_iter()
+                .map(|(turn_num, info)| (turn_num, info.messages.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages(0),
+            vec![(0, vec!["mine".to_string()]), (1, vec!["mine again".to_string()])]
+        );
+        assert_eq!(messages(1), vec![(0, vec!["theirs".to_string()])]);
+    }
+}
@@ -1,34 +1,82 @@
-use super::PlayerId;
+use planetwars_rules::{PlanetWars, PlayerId, Planet, Expedition};
 
-use super::pw_rules::{PlanetWars, Planet, Expedition};
+use super::pw_config::Visibility;
 use super::pw_protocol as proto;
 
 /// Serialize given gamestate
 pub fn serialize(state: &PlanetWars) -> proto::State {
-    serialize_rotated(state, 0)
+    let serializer = Serializer::new(state, 0, Visibility::default());
+    serializer.serialize_state()
 }
 
 /// Serialize given gamestate with player numbers rotated by given offset.
-pub fn serialize_rotated(state: &PlanetWars, offset: usize) -> proto::State {
-    let serializer = Serializer::new(state, offset);
-    serializer.serialize_state()
+/// This is the state as sent to a player, so it includes travel times, and
+/// shows enemy expeditions as far as `visibility` allows.
+pub fn serialize_rotated(state: &PlanetWars,
+                         offset: usize,
+                         visibility: Visibility)
+                         -> proto::State
+{
+    let serializer = Serializer::new(state, offset, visibility);
+    let mut state = serializer.serialize_state();
+    // the rotation makes every player see itself as player 1
+    state.player_id = Some(1);
+    state.travel_times = serializer.serialize_travel_times();
+    return state;
 }
 
 struct Serializer<'a> {
     state: &'a PlanetWars,
     player_num_offset: usize,
+    visibility: Visibility,
 }
 
 impl<'a> Serializer<'a> {
-    fn new(state: &'a PlanetWars, offset: usize) -> Self {
+    fn new(state: &'a PlanetWars, offset: usize, visibility: Visibility)
+        -> Self
+    {
         Serializer {
             state: state,
             player_num_offset: offset,
+            visibility: visibility,
         }
     }
 
+    fn serialize_travel_times(&self) -> Vec<proto::TravelTimes> {
+        let movement = &self.state.rules.movement;
+        let mut min_ships: Vec<u64> = movement.speeds
+            .iter()
+            .map(|bracket| bracket.min_ships)
+            .collect();
+        min_ships.sort();
+        min_ships.dedup();
+
+        min_ships.into_iter().map(|ship_count| {
+            let planets = &self.state.planets;
+            let turns = planets.iter().map(|origin| {
+                let times = planets.iter()
+                    .filter(|target| target.id != origin.id)
+                    .map(|target| {
+                        let time = movement.travel_time(origin, target, ship_count);
+                        (target.name.to_string(), time)
+                    }).collect();
+                (origin.name.to_string(), times)
+            }).collect();
+
+            proto::TravelTimes {
+                min_ships: ship_count,
+                turns: turns,
+            }
+        }).collect()
+    }
+
     fn serialize_state(&self) -> proto::State {
         proto::State {
+            turn_num: self.state.turn_num,
+            max_turns: self.state.max_turns,
+            num_players: self.state.players.len() as u64,
+            player_id: None,
+            time_budget: None,
             planets: self.state
                 .planets
                 .iter()
@@ -39,6 +87,7 @@ impl<'a> Serializer<'a> {
                 .iter()
                 .map(|exp| self.serialize_expedition(exp))
                 .collect(),
+            travel_times: Vec::new(),
         }
     }
 
@@ -54,22 +103,111 @@ impl<'a> Serializer<'a> {
 
     fn serialize_planet(&self, planet: &Planet) -> proto::Planet {
         proto::Planet {
-            name: planet.name.clone(),
+            name: planet.name.to_string(),
             x: planet.x,
             y: planet.y,
             owner: planet.owner().map(|id| self.player_num(id)),
             ship_count: planet.ship_count(),
+            growth_rate: planet.growth_rate,
+            radius: planet.radius,
         }
     }
 
     fn serialize_expedition(&self, exp: &Expedition) -> proto::Expedition {
+        let owner = self.player_num(exp.fleet.owner.unwrap());
+        let mut ship_count = exp.fleet.ship_count;
+        let mut destination = Some(self.state.planets[exp.target].name.to_string());
+        // player 1 is the player this state is for
+        if owner != 1 {
+            ship_count = self.visibility.ship_count(ship_count);
+            if self.visibility.hide_destinations {
+                destination = None;
+            }
+        }
+
         proto::Expedition {
             id: exp.id,
-            owner: self.player_num(exp.fleet.owner.unwrap()),
-            ship_count: exp.fleet.ship_count,
-            origin: self.state.planets[exp.origin].name.clone(),
-            destination: self.state.planets[exp.target].name.clone(),
+            owner: owner,
+            ship_count: ship_count,
+            origin: self.state.planets[exp.origin].name.to_string(),
+            destination: destination,
             turns_remaining: exp.turns_remaining,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use super::*;
+    use planetwars::Config;
+
+    const STATE: &str = r#"{
+        "planets": [
+            { "name": "protos", "x": 0, "y": 0, "owner": 1, "ship_count": 10 },
+            { "name": "duteros", "x": 4, "y": 0, "owner": 2, "ship_count": 10 }
+        ],
+        "expeditions": [
+            { "id": 0, "ship_count": 7, "origin": "protos",
+              "destination": "duteros", "owner": 1, "turns_remaining": 2 },
+            { "id": 1, "ship_count": 3, "origin": "duteros",
+              "destination": "protos", "owner": 2, "turns_remaining": 2 }
+        ]
+    }"#;
+
+    #[test]
+    fn restored_states_round_trip() {
+        // duteros was emptied by combat, but still belongs to player 2
+        let state = r#"{
+            "turn_num": 0,
+            "max_turns": 10,
+            "num_players": 2,
+            "planets": [
+                { "name": "protos", "x": 0.0, "y": 0.0, "owner": 1,
+                  "ship_count": 10, "growth_rate": 1 },
+                { "name": "duteros", "x": 4.0, "y": 0.0, "owner": 2,
+                  "ship_count": 0, "growth_rate": 1 },
+                { "name": "tritos", "x": 2.0, "y": 2.0, "owner": null,
+                  "ship_count": 0, "growth_rate": 1 }
+            ],
+            "expeditions": [
+                { "id": 4, "ship_count": 7, "origin": "protos",
+                  "destination": "duteros", "owner": 1, "turns_remaining": 2 }
+            ]
+        }"#;
+        let mut game = Config::restore_for_test(state, 2);
+        let expected: serde_json::Value = serde_json::from_str(state).unwrap();
+        assert_eq!(serde_json::to_value(&serialize(&game)).unwrap(), expected);
+
+        // and keeps growing
+        game.repopulate();
+        assert_eq!(game.planets[1].owner(), Some(PlayerId::new(1)));
+        assert_eq!(game.planets[1].ship_count(), 1);
+    }
+
+    #[test]
+    fn hidden_expeditions() {
+        let game = Config::restore_for_test(STATE, 2);
+        let visibility = Visibility {
+            hide_destinations: true,
+            ship_count_step: Some(5),
+        };
+
+        // as seen by the second player, who is player 1 in its own state
+        let seen = serialize_rotated(&game, 1, visibility);
+        let enemy = &seen.expeditions[0];
+        assert_eq!(enemy.owner, 2);
+        assert_eq!(enemy.destination, None);
+        assert_eq!(enemy.ship_count, 10);
+        let own = &seen.expeditions[1];
+        assert_eq!(own.owner, 1);
+        assert_eq!(own.destination, Some("protos".to_string()));
+        assert_eq!(own.ship_count, 3);
+
+        // logs show everything
+        let logged = serialize(&game);
+        assert_eq!(logged.expeditions[0].ship_count, 7);
+        assert_eq!(logged.expeditions[1].destination, Some("protos".to_string()));
+    }
+}
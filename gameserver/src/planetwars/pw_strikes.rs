@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use planetwars_rules::PlayerId;
+
+use super::pw_config::StrikeRules;
+use super::pw_protocol::PlayerAction;
+
+/// Keeps track of how players misbehave during a round, and decides when
+/// they are disqualified.
+pub struct Referee {
+    rules: StrikeRules,
+    strikes: HashMap<PlayerId, Strikes>,
+}
+
+#[derive(Default)]
+struct Strikes {
+    consecutive_timeouts: u64,
+    parse_errors: u64,
+    commands: u64,
+    invalid_commands: u64,
+}
+
+impl Referee {
+    pub fn new(rules: StrikeRules) -> Self {
+        Referee {
+            rules,
+            strikes: HashMap::new(),
+        }
+    }
+
+    /// Count the strikes in the action of a player. Returns why the player
+    /// is disqualified, when it went over one of the limits.
+    pub fn judge(&mut self, player_id: PlayerId, action: &PlayerAction)
+        -> Option<String>
+    {
+        let strikes = self.strikes.entry(player_id)
+            .or_insert_with(Strikes::default);
+
+        match action {
+            &PlayerAction::Timeout => {
+                strikes.consecutive_timeouts += 1;
+            }
+            &PlayerAction::ParseError(_) => {
+                strikes.consecutive_timeouts = 0;
+                strikes.parse_errors += 1;
+            }
+            &PlayerAction::Commands(ref commands) => {
+                strikes.consecutive_timeouts = 0;
+                strikes.commands += commands.len() as u64;
+                strikes.invalid_commands += commands.iter()
+                    .filter(|command| command.error.is_some())
+                    .count() as u64;
+            }
+        }
+
+        let rules = &self.rules;
+        if let Some(max) = rules.max_consecutive_timeouts {
+            if strikes.consecutive_timeouts > max {
+                return Some(format!(
+                    "timed out {} turns in a row",
+                    strikes.consecutive_timeouts,
+                ));
+            }
+        }
+        if let Some(max) = rules.max_parse_errors {
+            if strikes.parse_errors > max {
+                return Some(format!(
+                    "sent {} responses that could not be parsed",
+                    strikes.parse_errors,
+                ));
+            }
+        }
+        if let Some(max) = rules.max_invalid_ratio {
+            let ratio = strikes.invalid_commands as f64 / strikes.commands as f64;
+            if strikes.commands >= rules.min_commands && ratio > max {
+                return Some(format!(
+                    "{} of {} commands were invalid",
+                    strikes.invalid_commands,
+                    strikes.commands,
+                ));
+            }
+        }
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::pw_protocol::{Command, CommandError, PlayerCommand};
+
+    fn commands(valid: usize, invalid: usize) -> PlayerAction {
+        let command = |error| PlayerCommand {
+            command: Command {
+                origin: "a".to_string(),
+                destination: "b".to_string(),
+                ship_count: 1,
+            },
+            error,
+        };
+        let valid = (0..valid).map(|_| command(None));
+        let invalid = (0..invalid)
+            .map(|_| command(Some(CommandError::NotEnoughShips)));
+        PlayerAction::Commands(valid.chain(invalid).collect())
+    }
+
+    #[test]
+    fn no_limits() {
+        let mut referee = Referee::new(StrikeRules::default());
+        let player = PlayerId::new(0);
+        for _ in 0..100 {
+            assert_eq!(referee.judge(player, &PlayerAction::Timeout), None);
+            assert_eq!(referee.judge(player, &commands(0, 5)), None);
+        }
+    }
+
+    #[test]
+    fn consecutive_timeouts() {
+        let mut rules = StrikeRules::default();
+        rules.max_consecutive_timeouts = Some(2);
+        let mut referee = Referee::new(rules);
+        let (a, b) = (PlayerId::new(0), PlayerId::new(1));
+
+        assert_eq!(referee.judge(a, &PlayerAction::Timeout), None);
+        assert_eq!(referee.judge(a, &PlayerAction::Timeout), None);
+        // a response resets the count
+        assert_eq!(referee.judge(a, &commands(1, 0)), None);
+        assert_eq!(referee.judge(a, &PlayerAction::Timeout), None);
+        assert_eq!(referee.judge(b, &PlayerAction::Timeout), None);
+        assert_eq!(referee.judge(a, &PlayerAction::Timeout), None);
+        assert!(referee.judge(a, &PlayerAction::Timeout).is_some());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let mut rules = StrikeRules::default();
+        rules.max_parse_errors = Some(1);
+        let mut referee = Referee::new(rules);
+        let player = PlayerId::new(0);
+        let garbage = PlayerAction::ParseError("expected value".to_string());
+
+        assert_eq!(referee.judge(player, &garbage), None);
+        assert_eq!(referee.judge(player, &commands(3, 0)), None);
+        assert!(referee.judge(player, &garbage).is_some());
+    }
+
+    #[test]
+    fn invalid_ratio() {
+        let mut rules = StrikeRules::default();
+        rules.max_invalid_ratio = Some(0.5);
+        rules.min_commands = 4;
+        let mut referee = Referee::new(rules);
+        let player = PlayerId::new(0);
+
+        // too few commands to judge
+        assert_eq!(referee.judge(player, &commands(0, 3)), None);
+        assert_eq!(referee.judge(player, &commands(3, 0)), None);
+        assert_eq!(referee.judge(player, &commands(0, 0)), None);
+        assert!(referee.judge(player, &commands(0, 1)).is_some());
+    }
+}
@@ -0,0 +1,71 @@
+extern crate map_generator;
+extern crate mozaic_bot_driver;
+extern crate serde_json;
+
+use std::env;
+use std::fs;
+
+use map_generator::{create_seeded_map, Symmetry};
+use mozaic_bot_driver::planetwars::{Config, Map};
+
+/// Generate maps with given generator config, and check that the server
+/// loads them as they were generated.
+fn check_generated_maps(generator: map_generator::Config) {
+    for seed in 0..10 {
+        let map = create_seeded_map(&generator, seed).unwrap();
+        let path = env::temp_dir().join(format!(
+            "mozaic_generated_map_{}_{}.json",
+            seed,
+            map.num_seats(),
+        ));
+        map.write(&path).unwrap();
+
+        // serde_json may round the last digit of a coordinate
+        let read = Map::read(&path).unwrap();
+        assert_eq!(read.planets.len(), map.planets.len());
+        for (read, generated) in read.planets.iter().zip(map.planets.iter()) {
+            assert_eq!(read.name, generated.name);
+            assert!(close(read.x, generated.x) && close(read.y, generated.y));
+            assert_eq!(read.owner, generated.owner);
+            assert_eq!(read.ship_count, generated.ship_count);
+        }
+
+        let config: Config = serde_json::from_value(json_config(&path)).unwrap();
+        let num_players = map.num_seats() as usize;
+        let game = config.create_game(num_players, None).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(game.planets.len(), map.planets.len());
+        for (loaded, generated) in game.planets.iter().zip(map.planets.iter()) {
+            assert_eq!(&*loaded.name, generated.name.as_str());
+            assert!(close(loaded.x, generated.x) && close(loaded.y, generated.y));
+            assert_eq!(loaded.ship_count(), generated.ship_count);
+            let owner = loaded.owner().map(|id| id.as_usize() as u64 + 1);
+            assert_eq!(owner, generated.owner);
+        }
+    }
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+fn json_config(map_file: &::std::path::Path) -> serde_json::Value {
+    let mut config = serde_json::Map::new();
+    config.insert("map_file".to_string(), map_file.to_str().unwrap().into());
+    config.insert("max_turns".to_string(), 100.into());
+    serde_json::Value::Object(config)
+}
+
+#[test]
+fn uniform_maps_load() {
+    check_generated_maps(map_generator::Config::new());
+}
+
+#[test]
+fn symmetric_maps_load() {
+    let mut generator = map_generator::Config::new();
+    generator.player_amount = map_generator::Bound { min: 2, max: 5 };
+    generator.symmetry = Some(Symmetry::Rotational);
+    check_generated_maps(generator);
+}
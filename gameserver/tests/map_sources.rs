@@ -0,0 +1,128 @@
+extern crate mozaic_bot_driver;
+#[macro_use]
+extern crate serde_json;
+
+use std::path::Path;
+
+use mozaic_bot_driver::planetwars::{Config, PlanetWars};
+
+const MAPS: &str = "../planetwars/maps";
+
+fn create_game(config: serde_json::Value, num_players: usize)
+    -> Result<PlanetWars, String>
+{
+    let config: Config = serde_json::from_value(config).unwrap();
+    config.create_game(num_players, Some(Path::new(MAPS)))
+        .map_err(|err| err.to_string())
+}
+
+fn load_error(config: serde_json::Value) -> String {
+    match create_game(config, 2) {
+        Ok(_) => panic!("map loaded"),
+        Err(err) => err,
+    }
+}
+
+#[test]
+fn map_file() {
+    let game = create_game(json!({
+        "map_file": format!("{}/hex.json", MAPS),
+        "max_turns": 100,
+    }), 2).unwrap();
+    assert!(!game.planets.is_empty());
+}
+
+#[test]
+fn inline_map() {
+    let game = create_game(json!({
+        "map": { "planets": [
+            { "name": "a", "x": 0, "y": 0, "owner": 1, "ship_count": 5 },
+            { "name": "b", "x": 4, "y": 0, "owner": 2, "ship_count": 5 },
+            { "name": "c", "x": 2, "y": 1, "ship_count": 3 },
+        ]},
+        "max_turns": 100,
+    }), 2).unwrap();
+    let names: Vec<_> = game.planets.iter().map(|p| &*p.name).collect();
+    assert_eq!(names, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn pool_map() {
+    let game = create_game(json!({
+        "map_name": "hex",
+        "max_turns": 100,
+    }), 2).unwrap();
+    assert!(!game.planets.is_empty());
+}
+
+#[test]
+fn pool_names_stay_in_the_pool() {
+    for name in ["../maps/hex", "/etc/passwd", ".hidden", ""].iter() {
+        let err = load_error(json!({
+            "map_name": name,
+            "max_turns": 100,
+        }));
+        assert!(err.contains("invalid map name"), "{}", err);
+    }
+}
+
+#[test]
+fn generated_map_fits_the_players() {
+    for &num_players in [2, 3, 4].iter() {
+        let game = create_game(json!({
+            "generate_map": { "symmetry": "rotational" },
+            "max_turns": 100,
+            "seed": 7,
+        }), num_players).unwrap();
+        for id in 0..num_players {
+            let starts = game.planets.iter()
+                .filter(|p| p.owner().map(|owner| owner.as_usize()) == Some(id))
+                .count();
+            assert_eq!(starts, 1);
+        }
+    }
+}
+
+#[test]
+fn missing_map_is_an_error() {
+    let err = load_error(json!({
+        "map_file": "no/such/map.json",
+        "max_turns": 100,
+    }));
+    assert!(err.contains("no/such/map.json"), "{}", err);
+}
+
+#[test]
+fn unusable_generator_is_an_error() {
+    let err = load_error(json!({
+        "generate_map": { "min_spacing": 1000, "symmetry": "rotational" },
+        "max_turns": 10,
+    }));
+    assert!(err.contains("no room for start planets"), "{}", err);
+
+    let err = load_error(json!({
+        "generate_map": { "planet_amount": { "min": 5, "max": 5 } },
+        "max_turns": 10,
+    }));
+    assert!(err.contains("planet_amount"), "{}", err);
+}
+
+#[test]
+fn neutral_growth_warnings() {
+    let warnings = |neutral_ships: u64| {
+        let config: Config = serde_json::from_value(json!({
+            "map": { "planets": [
+                { "name": "a", "x": 0, "y": 0, "owner": 1, "ship_count": 5 },
+                { "name": "b", "x": 4, "y": 0, "owner": 2, "ship_count": 5 },
+                { "name": "c", "x": 2, "y": 2, "ship_count": neutral_ships }
+            ]},
+            "neutrals": { "growth": true, "max_ships": 3 },
+            "max_turns": 10,
+        })).unwrap();
+        let game = config.create_game(2, None).unwrap();
+        config.warnings(&game)
+    };
+    // an empty neutral planet can still grow
+    assert!(warnings(0).is_empty());
+    assert_eq!(warnings(3).len(), 1);
+}
@@ -0,0 +1,7 @@
+extern crate prost_build;
+
+fn main() {
+    println!("cargo:rerun-if-changed=../proto/core.proto");
+    prost_build::compile_protos(&["../proto/core.proto"],
+                                &[".."]).unwrap();
+}
\ No newline at end of file
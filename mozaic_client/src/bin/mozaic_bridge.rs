@@ -0,0 +1,193 @@
+//! Connects a bot that speaks newline-delimited JSON on stdin and stdout to
+//! a game server.
+//!
+//! Every game state the server sends is written to the bot as a single line,
+//! and the first line the bot answers with is sent back as its action.
+
+extern crate hex;
+extern crate mozaic_client;
+extern crate serde_json;
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process;
+use std::time::{Duration, Instant};
+
+use mozaic_client::{Client, Message};
+use mozaic_client::bot_process::BotProcess;
+use mozaic_client::planetwars::{Action, PlayerAction, ServerMessage};
+
+const USAGE: &str = "\
+usage: mozaic_bridge [--deadline <ms>] [--log <file>] \
+<address> <token> <command> [args...]
+
+  --deadline <ms>  time the bot gets to answer a game state (default 900)
+  --log <file>     append the bot's stderr to this file";
+
+/// The server allows one second per turn; leave some margin for the network.
+const DEFAULT_DEADLINE: u64 = 900;
+
+struct BridgeConfig {
+    deadline: Duration,
+    log_file: Option<String>,
+    address: String,
+    token: Vec<u8>,
+    command: String,
+    args: Vec<String>,
+}
+
+fn main() {
+    let config = match parse_args(env::args().skip(1).collect()) {
+        Ok(config) => config,
+        Err(msg) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            process::exit(1);
+        }
+    };
+
+    if let Err(err) = run(config) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: Vec<String>) -> Result<BridgeConfig, String> {
+    let mut deadline = DEFAULT_DEADLINE;
+    let mut log_file = None;
+
+    while !args.is_empty() && args[0].starts_with("--") {
+        let flag = args.remove(0);
+        if args.is_empty() {
+            return Err(format!("missing value for {}", flag));
+        }
+        let value = args.remove(0);
+        match flag.as_str() {
+            "--deadline" => {
+                deadline = value.parse()
+                    .map_err(|_| format!("invalid deadline: {}", value))?;
+            }
+            "--log" => log_file = Some(value),
+            _ => return Err(format!("unknown flag: {}", flag)),
+        }
+    }
+
+    if args.len() < 3 {
+        return Err("expected an address, a token and a bot command".to_string());
+    }
+    let address = args.remove(0);
+    let token = hex::decode(args.remove(0))
+        .map_err(|err| format!("invalid token: {}", err))?;
+    let command = args.remove(0);
+
+    Ok(BridgeConfig {
+        deadline: Duration::from_millis(deadline),
+        log_file,
+        address,
+        token,
+        command,
+        args,
+    })
+}
+
+fn run(config: BridgeConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut log, bot_log): (Option<Box<dyn Write + Send>>, _) =
+        match config.log_file {
+            Some(ref path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)?;
+                // bridge diagnostics and bot output end up in the same file.
+                let bot_log = file.try_clone()?;
+                (Some(Box::new(file)), Some(bot_log))
+            }
+            None => (None, None),
+        };
+
+    let mut client = Client::connect(config.address.as_str(), &config.token)?;
+    let mut bot = BotProcess::spawn(&config.command, &config.args, bot_log)?;
+
+    while let Some(message) = client.recv()? {
+        let (message_id, data) = match message {
+            Message::Message { message_id, data } => (message_id, data),
+            Message::Response { .. } => continue,
+        };
+
+        match serde_json::from_slice(&data)? {
+            ServerMessage::GameState(state) => {
+                let received = Instant::now();
+                let line = serde_json::to_string(&state)?;
+                let response = match bot.request(&line, config.deadline)? {
+                    Some(response) => response,
+                    None => {
+                        debug(&mut log, &format!(
+                            "bot did not answer within {} ms, passing turn",
+                            duration_ms(received.elapsed()),
+                        ))?;
+                        let action = Action::new(Vec::new());
+                        serde_json::to_string(&action)?
+                    }
+                };
+                client.respond(message_id, response.into_bytes())?;
+            }
+            ServerMessage::PlayerAction(action) => {
+                if let Some(msg) = describe_failure(&action) {
+                    debug(&mut log, &msg)?;
+                }
+            }
+            ServerMessage::FinalState(_state) => {
+                break;
+            }
+            // bots only ever read game states
+            ServerMessage::GameInfo(_info) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Write a bridge diagnostic to the debug log.
+fn debug(log: &mut Option<Box<dyn Write + Send>>, msg: &str)
+    -> std::io::Result<()>
+{
+    match *log {
+        Some(ref mut log) => {
+            writeln!(log, "[mozaic_bridge] {}", msg)?;
+            log.flush()
+        }
+        None => {
+            eprintln!("[mozaic_bridge] {}", msg);
+            Ok(())
+        }
+    }
+}
+
+/// Describe what went wrong with an action, if anything did.
+fn describe_failure(action: &PlayerAction) -> Option<String> {
+    match *action {
+        PlayerAction::Timeout => Some("server reported a timeout".to_string()),
+        PlayerAction::ParseError(ref err) => {
+            Some(format!("server could not parse action: {}", err))
+        }
+        PlayerAction::Commands(ref commands) => {
+            let errors: Vec<String> = commands.iter()
+                .filter_map(|cmd| cmd.error.as_ref().map(|err| {
+                    format!("{} -> {} ({} ships): {:?}",
+                        cmd.command.origin,
+                        cmd.command.destination,
+                        cmd.command.ship_count,
+                        err)
+                }))
+                .collect();
+            if errors.is_empty() {
+                None
+            } else {
+                Some(format!("invalid commands: {}", errors.join(", ")))
+            }
+        }
+    }
+}
+
+fn duration_ms(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + u64::from(duration.subsec_nanos()) / 1_000_000
+}
@@ -0,0 +1,103 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// A bot running as a child process, speaking newline-delimited messages
+/// over its stdin and stdout.
+pub struct BotProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl BotProcess {
+    /// Spawn the bot. Everything the bot writes to stderr is copied to
+    /// `debug_log`, if given, and passed through otherwise.
+    pub fn spawn<W>(command: &str, args: &[String], debug_log: Option<W>)
+        -> io::Result<Self>
+        where W: Write + Send + 'static
+    {
+        let stderr = match debug_log {
+            Some(_) => Stdio::piped(),
+            None => Stdio::inherit(),
+        };
+        let mut child = Command::new(command)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (snd, rcv) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if snd.send(line).is_err() { return },
+                    Err(_) => return,
+                }
+            }
+        });
+
+        if let Some(mut log) = debug_log {
+            let stderr = child.stderr.take().unwrap();
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines() {
+                    let res = line.and_then(|line| {
+                        writeln!(log, "{}", line)?;
+                        log.flush()
+                    });
+                    if res.is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        Ok(BotProcess {
+            child,
+            stdin,
+            lines: rcv,
+        })
+    }
+
+    /// Write a line to the bot, and wait at most `timeout` for its answer.
+    /// Returns `Ok(None)` when the bot did not answer in time.
+    ///
+    /// Lines the bot wrote before this request (for example a late answer to
+    /// a previous request) are discarded.
+    pub fn request(&mut self, line: &str, timeout: Duration)
+        -> io::Result<Option<String>>
+    {
+        while let Ok(_stale) = self.lines.try_recv() {}
+
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(Some(line)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "bot exited"))
+            }
+        }
+    }
+
+    pub fn kill(&mut self) -> io::Result<()> {
+        self.child.kill()?;
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+impl Drop for BotProcess {
+    fn drop(&mut self) {
+        // the bot might have exited already, in which case there is nothing
+        // to clean up.
+        let _ = self.kill();
+    }
+}
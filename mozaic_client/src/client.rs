@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+use std::net::ToSocketAddrs;
+
+use prost::Message as ProtobufMessage;
+
+use connection::Connection;
+use errors::{ErrorKind, Result};
+use protocol::message as proto;
+use protocol::Message as MessageWrapper;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct MessageId(pub u64);
+
+/// A message received from the server.
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// A message sent by the server. When the server is expecting an
+    /// answer, it should be answered with `Client::respond`.
+    Message {
+        message_id: MessageId,
+        data: Vec<u8>,
+    },
+    /// A response to a message this client sent.
+    Response {
+        message_id: MessageId,
+        data: Vec<u8>,
+    },
+}
+
+/// Wraps a connection, keeping track of message ids so that requests and
+/// responses can be matched.
+pub struct Client {
+    connection: Connection,
+    message_counter: u64,
+    // messages that arrived while waiting for a response
+    queued: VecDeque<Message>,
+}
+
+impl Client {
+    pub fn new(connection: Connection) -> Self {
+        Client {
+            connection,
+            message_counter: 0,
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// Connect to a game server, identifying with given token.
+    pub fn connect<A>(addr: A, token: &[u8]) -> Result<Self>
+        where A: ToSocketAddrs
+    {
+        Connection::connect(addr, token).map(Client::new)
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Send a message to the server.
+    pub fn send(&mut self, data: Vec<u8>) -> Result<MessageId> {
+        let message_id = self.next_message_id();
+        let MessageId(id) = message_id;
+        let message = proto::Message { message_id: id, data };
+        self.send_payload(proto::Payload::Message(message))?;
+        Ok(message_id)
+    }
+
+    /// Answer a message the server sent.
+    pub fn respond(&mut self, message_id: MessageId, data: Vec<u8>)
+        -> Result<()>
+    {
+        let MessageId(id) = message_id;
+        let response = proto::Response { message_id: id, data };
+        self.send_payload(proto::Payload::Response(response))
+    }
+
+    /// Send a message to the server and wait for its response.
+    /// Messages that arrive in the meantime are queued, and will be returned
+    /// by subsequent calls to `recv`.
+    pub fn request(&mut self, data: Vec<u8>) -> Result<Vec<u8>> {
+        let request_id = self.send(data)?;
+        loop {
+            match self.read_message()? {
+                None => bail!(::std::io::Error::from(
+                    ::std::io::ErrorKind::ConnectionAborted
+                )),
+                Some(Message::Response { message_id, data }) => {
+                    if message_id == request_id {
+                        return Ok(data);
+                    }
+                }
+                Some(message) => self.queued.push_back(message),
+            }
+        }
+    }
+
+    /// Receive the next message.
+    /// Returns `None` when the server closed the connection.
+    pub fn recv(&mut self) -> Result<Option<Message>> {
+        if let Some(message) = self.queued.pop_front() {
+            return Ok(Some(message));
+        }
+        self.read_message()
+    }
+
+    fn read_message(&mut self) -> Result<Option<Message>> {
+        let data = match self.connection.recv()? {
+            None => return Ok(None),
+            Some(data) => data,
+        };
+
+        let message = match MessageWrapper::decode(data)?.payload {
+            None => bail!(ErrorKind::EmptyMessage),
+            Some(proto::Payload::Message(message)) => Message::Message {
+                message_id: MessageId(message.message_id),
+                data: message.data,
+            },
+            Some(proto::Payload::Response(response)) => Message::Response {
+                message_id: MessageId(response.message_id),
+                data: response.data,
+            },
+        };
+        Ok(Some(message))
+    }
+
+    fn send_payload(&mut self, payload: proto::Payload) -> Result<()> {
+        let message = MessageWrapper { payload: Some(payload) };
+        let mut bytes = Vec::with_capacity(message.encoded_len());
+        // encoding can only fail because the buffer does not have
+        // enough space allocated, but we just allocated the required
+        // space.
+        message.encode(&mut bytes).unwrap();
+        self.connection.send(bytes)
+    }
+
+    fn next_message_id(&mut self) -> MessageId {
+        let message_num = self.message_counter;
+        self.message_counter += 1;
+        MessageId(message_num)
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::{self, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use prost::Message;
+use prost::encoding;
+
+use errors::{ErrorKind, Result};
+use protocol::{self as proto, Packet, packet};
+use protocol::connection_response::Response;
+
+/// A framed connection to a game server.
+///
+/// Every frame on the wire is a protobuf message, prefixed with its length
+/// encoded as a varint. The first frame a client sends is a
+/// `ConnectionRequest`; after the server has accepted it, all traffic is
+/// wrapped in `Packet`s.
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Connect to a game server, identifying with given token.
+    pub fn connect<A>(addr: A, token: &[u8]) -> Result<Self>
+        where A: ToSocketAddrs
+    {
+        let stream = TcpStream::connect(addr)?;
+        Connection::handshake(stream, token)
+    }
+
+    /// Perform the connection handshake over an already established stream.
+    pub fn handshake(stream: TcpStream, token: &[u8]) -> Result<Self> {
+        let mut connection = Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+
+        let request = proto::ConnectionRequest { token: token.to_vec() };
+        write_frame(&mut connection.writer, &request)?;
+
+        let data = match read_frame(&mut connection.reader)? {
+            None => bail!(io::Error::from(io::ErrorKind::ConnectionAborted)),
+            Some(data) => data,
+        };
+        let response = proto::ConnectionResponse::decode(data)?;
+        match response.response {
+            Some(Response::Success(_)) => Ok(connection),
+            Some(Response::Error(error)) => {
+                bail!(ErrorKind::ConnectionRefused(error.message))
+            }
+            None => bail!(ErrorKind::EmptyMessage),
+        }
+    }
+
+    /// The underlying tcp stream, for setting timeouts and the like.
+    pub fn stream(&self) -> &TcpStream {
+        &self.writer
+    }
+
+    /// Send a data packet.
+    pub fn send(&mut self, data: Vec<u8>) -> Result<()> {
+        let packet = Packet {
+            payload: Some(packet::Payload::Message(packet::Message { data })),
+        };
+        write_frame(&mut self.writer, &packet)
+    }
+
+    /// Receive the next data packet.
+    /// Returns `None` when the server closed the connection.
+    pub fn recv(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            let data = match read_frame(&mut self.reader)? {
+                None => return Ok(None),
+                Some(data) => data,
+            };
+            match Packet::decode(data)?.payload {
+                Some(packet::Payload::Message(message)) => {
+                    return Ok(Some(message.data));
+                }
+                Some(packet::Payload::CloseConnection(_)) => return Ok(None),
+                // skip empty packets
+                None => {}
+            }
+        }
+    }
+}
+
+/// Write a length-delimited protobuf message.
+pub fn write_frame<W, M>(writer: &mut W, message: &M) -> Result<()>
+    where W: Write,
+          M: Message
+{
+    let len = message.encoded_len();
+    let mut bytes = Vec::with_capacity(encoding::encoded_len_varint(len as u64) + len);
+    encoding::encode_varint(len as u64, &mut bytes);
+    // encoding can only fail because the buffer does not have
+    // enough space allocated, but we just allocated the required
+    // space.
+    message.encode(&mut bytes).unwrap();
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read the contents of a length-delimited frame.
+/// Returns `None` when the stream ended cleanly before a new frame started.
+pub fn read_frame<R>(reader: &mut R) -> Result<Option<Vec<u8>>>
+    where R: Read
+{
+    let len = match read_varint(reader)? {
+        None => return Ok(None),
+        Some(len) => len,
+    };
+    let mut buf = vec![0; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(Some(buf))
+}
+
+fn read_varint<R>(reader: &mut R) -> Result<Option<u64>>
+    where R: Read
+{
+    let mut value = 0;
+    for i in 0..10 {
+        let mut byte = [0];
+        match reader.read_exact(&mut byte) {
+            Err(ref err) if i == 0 && err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(None);
+            }
+            res => res?,
+        }
+        value |= u64::from(byte[0] & 0x7F) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    bail!(ErrorKind::InvalidVarint)
+}
@@ -0,0 +1,27 @@
+// error_chain expands to the deprecated `Error::description` and `cause`.
+#![allow(deprecated)]
+
+error_chain! {
+    types {
+        Error, ErrorKind, ResultExt, Result;
+    }
+
+    errors {
+        ConnectionRefused(reason: String) {
+            description("connection refused"),
+            display("connection refused: {}", reason),
+        }
+        EmptyMessage {
+            description("received an empty message"),
+        }
+        InvalidVarint {
+            description("invalid LEB128 number"),
+        }
+    }
+
+    foreign_links {
+        Io(::std::io::Error);
+        Decode(::prost::DecodeError);
+        Json(::serde_json::Error);
+    }
+}
@@ -0,0 +1,45 @@
+//! Client side of the MOZAIC wire protocol.
+//!
+//! This crate implements the connection handshake, packet framing and
+//! message ids, so that bots written in Rust (and tests that have to talk
+//! to a game server) do not have to reimplement them.
+//!
+//! ```no_run
+//! use mozaic_client::Client;
+//! use mozaic_client::planetwars::{self, Bot, State, Action};
+//!
+//! struct SilentBot;
+//!
+//! impl Bot for SilentBot {
+//!     fn step(&mut self, _state: &State) -> Action {
+//!         Action::new(Vec::new())
+//!     }
+//! }
+//!
+//! let mut client = Client::connect("127.0.0.1:9142", b"\xab\xba").unwrap();
+//! planetwars::run_bot(&mut client, &mut SilentBot).unwrap();
+//! ```
+
+extern crate bytes;
+/// The game rules, so bots can simulate what their moves will do.
+pub extern crate planetwars_rules;
+#[macro_use]
+extern crate error_chain;
+extern crate prost;
+#[macro_use]
+extern crate prost_derive;
+extern crate serde_json;
+
+pub mod protocol {
+    include!(concat!(env!("OUT_DIR"), "/mozaic.protocol.rs"));
+}
+
+pub mod errors;
+pub mod connection;
+pub mod client;
+pub mod planetwars;
+pub mod bot_process;
+
+pub use errors::{Error, ErrorKind, Result};
+pub use connection::Connection;
+pub use client::{Client, Message, MessageId};
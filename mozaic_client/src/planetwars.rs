@@ -0,0 +1,70 @@
+//! The planet wars game protocol, as seen from a bot.
+
+use serde_json;
+
+use client::{Client, Message};
+use errors::Result;
+
+/// The messages are shared with the server. To simulate turns on a game
+/// state, see `planetwars_rules::PlanetWars::from_state`.
+pub use planetwars_rules::protocol::{
+    State,
+    Planet,
+    Expedition,
+    TravelTimes,
+    Action,
+    DebugInfo,
+    Annotation,
+    Command,
+    CommandError,
+    PlayerCommand,
+    PlayerAction,
+    GameInfo,
+    PlayerInfo,
+    ServerMessage,
+};
+
+/// A planet wars bot.
+pub trait Bot {
+    /// Decide on the action to take in given state.
+    fn step(&mut self, state: &State) -> Action;
+
+    /// Called with the outcome of the previous action.
+    fn on_action(&mut self, _action: &PlayerAction) {}
+
+    /// Called before the first turn. In a match with swapped seats, this is
+    /// called again at the start of every rematch.
+    fn on_game_info(&mut self, _info: &GameInfo) {}
+}
+
+/// Play a match with given bot, until the server ends the game.
+/// Returns the final game state, or `None` when the connection closed
+/// before the game was over.
+pub fn run_bot<B>(client: &mut Client, bot: &mut B) -> Result<Option<State>>
+    where B: Bot
+{
+    while let Some(message) = client.recv()? {
+        let (message_id, data) = match message {
+            Message::Message { message_id, data } => (message_id, data),
+            // we never send requests, so there is nothing to resolve.
+            Message::Response { .. } => continue,
+        };
+
+        match serde_json::from_slice(&data)? {
+            ServerMessage::GameState(state) => {
+                let action = bot.step(&state);
+                client.respond(message_id, serde_json::to_vec(&action)?)?;
+            }
+            ServerMessage::PlayerAction(action) => {
+                bot.on_action(&action);
+            }
+            ServerMessage::FinalState(state) => {
+                return Ok(Some(state));
+            }
+            ServerMessage::GameInfo(info) => {
+                bot.on_game_info(&info);
+            }
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,22 @@
+extern crate mozaic_client;
+
+use std::fs::File;
+use std::time::Duration;
+
+use mozaic_client::bot_process::BotProcess;
+
+#[test]
+fn relays_lines() {
+    let mut bot = BotProcess::spawn::<File>("cat", &[], None).unwrap();
+    let timeout = Duration::from_secs(5);
+    assert_eq!(bot.request("hello", timeout).unwrap(), Some("hello".to_string()));
+    assert_eq!(bot.request("world", timeout).unwrap(), Some("world".to_string()));
+}
+
+#[test]
+fn times_out() {
+    let args = vec!["-c".to_string(), "sleep 5".to_string()];
+    let mut bot = BotProcess::spawn::<File>("sh", &args, None).unwrap();
+    let timeout = Duration::from_millis(50);
+    assert_eq!(bot.request("hello", timeout).unwrap(), None);
+}
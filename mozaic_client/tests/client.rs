@@ -0,0 +1,184 @@
+extern crate mozaic_client;
+extern crate prost;
+extern crate serde_json;
+
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use prost::Message as ProtobufMessage;
+
+use mozaic_client::{Client, ErrorKind};
+use mozaic_client::connection::{read_frame, write_frame};
+use mozaic_client::planetwars::{self, Action, Bot, Command, DebugInfo, State};
+use mozaic_client::protocol::{self as proto, connection_response, packet};
+use mozaic_client::protocol::message::{self, Payload};
+
+const TOKEN: &[u8] = b"\xab\xba";
+
+/// Accept one connection and answer its handshake.
+fn accept(listener: &TcpListener) -> (TcpStream, Vec<u8>) {
+    let (mut stream, _addr) = listener.accept().unwrap();
+    let data = read_frame(&mut stream).unwrap().unwrap();
+    let request = proto::ConnectionRequest::decode(data).unwrap();
+
+    let response = if request.token == TOKEN {
+        connection_response::Response::Success(proto::ConnectionSuccess {})
+    } else {
+        connection_response::Response::Error(proto::ConnectionError {
+            message: "invalid token".to_string(),
+        })
+    };
+    let response = proto::ConnectionResponse { response: Some(response) };
+    write_frame(&mut stream, &response).unwrap();
+    (stream, request.token)
+}
+
+fn send_message(stream: &mut TcpStream, message_id: u64, data: Vec<u8>) {
+    let message = proto::Message {
+        payload: Some(Payload::Message(message::Message { message_id, data })),
+    };
+    let mut bytes = Vec::new();
+    message.encode(&mut bytes).unwrap();
+    let packet = proto::Packet {
+        payload: Some(packet::Payload::Message(packet::Message { data: bytes })),
+    };
+    write_frame(stream, &packet).unwrap();
+}
+
+fn recv_message(stream: &mut TcpStream) -> Payload {
+    let data = read_frame(stream).unwrap().unwrap();
+    let packet = proto::Packet::decode(data).unwrap();
+    let data = match packet.payload {
+        Some(packet::Payload::Message(message)) => message.data,
+        _ => panic!("expected a message packet"),
+    };
+    proto::Message::decode(data).unwrap().payload.unwrap()
+}
+
+const STATE: &str = r#"{
+    "planets": [
+        { "name": "protos", "x": -6, "y": 0, "owner": 1, "ship_count": 6 },
+        { "name": "duteros", "x": -3, "y": 5, "ship_count": 6 }
+    ],
+    "expeditions": [],
+    "travel_times": [
+        {
+            "min_ships": 0,
+            "turns": {
+                "protos": { "duteros": 6 },
+                "duteros": { "protos": 6 }
+            }
+        }
+    ]
+}"#;
+
+struct SendAllBot;
+
+impl Bot for SendAllBot {
+    fn step(&mut self, state: &State) -> Action {
+        let origin = state.planets.iter()
+            .find(|p| p.owner == Some(1))
+            .unwrap();
+        assert_eq!(state.travel_time(&origin.name, "duteros", 6), Some(6));
+        let mut action = Action::new(vec![Command {
+            origin: origin.name.clone(),
+            destination: "duteros".to_string(),
+            ship_count: origin.ship_count,
+        }]);
+        action.debug = Some(DebugInfo {
+            messages: vec!["all in".to_string()],
+            annotations: Vec::new(),
+        });
+        action
+    }
+}
+
+#[test]
+fn plays_a_turn() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _token) = accept(&listener);
+
+        let msg = format!(r#"{{"type":"game_state","content":{}}}"#, STATE);
+        send_message(&mut stream, 7, msg.into_bytes());
+        let response = match recv_message(&mut stream) {
+            Payload::Response(response) => response,
+            _ => panic!("expected a response"),
+        };
+        assert_eq!(response.message_id, 7);
+        let action: serde_json::Value =
+            serde_json::from_slice(&response.data).unwrap();
+        assert_eq!(action["moves"][0]["ship_count"], 6);
+        assert_eq!(action["debug"]["messages"][0], "all in");
+
+        let msg = format!(r#"{{"type":"final_state","content":{}}}"#, STATE);
+        send_message(&mut stream, 8, msg.into_bytes());
+    });
+
+    let mut client = Client::connect(addr, TOKEN).unwrap();
+    let state = planetwars::run_bot(&mut client, &mut SendAllBot).unwrap();
+    assert_eq!(state.unwrap().planets.len(), 2);
+    server.join().unwrap();
+}
+
+#[test]
+fn request_ids_are_matched() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (mut stream, _token) = accept(&listener);
+        let request = match recv_message(&mut stream) {
+            Payload::Message(message) => message,
+            _ => panic!("expected a message"),
+        };
+        // interleave a message before answering the request
+        send_message(&mut stream, 0, b"interleaved".to_vec());
+        let response = proto::Message {
+            payload: Some(Payload::Response(message::Response {
+                message_id: request.message_id,
+                data: b"pong".to_vec(),
+            })),
+        };
+        let mut bytes = Vec::new();
+        response.encode(&mut bytes).unwrap();
+        let packet = proto::Packet {
+            payload: Some(packet::Payload::Message(packet::Message { data: bytes })),
+        };
+        write_frame(&mut stream, &packet).unwrap();
+    });
+
+    let mut client = Client::connect(addr, TOKEN).unwrap();
+    assert_eq!(client.request(b"ping".to_vec()).unwrap(), b"pong");
+    match client.recv().unwrap() {
+        Some(mozaic_client::Message::Message { data, .. }) => {
+            assert_eq!(data, b"interleaved");
+        }
+        _ => panic!("interleaved message was lost"),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn refused_token() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (_stream, token) = accept(&listener);
+        assert_eq!(token, b"nope");
+    });
+
+    match Client::connect(addr, b"nope") {
+        Err(err) => match *err.kind() {
+            ErrorKind::ConnectionRefused(ref reason) => {
+                assert_eq!(reason, "invalid token");
+            }
+            ref kind => panic!("unexpected error: {}", kind),
+        },
+        Ok(_) => panic!("connection should have been refused"),
+    }
+    server.join().unwrap();
+}
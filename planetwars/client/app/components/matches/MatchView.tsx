@@ -194,12 +194,22 @@ export class MatchViewer extends React.Component<Props, State> {
             viewState={viewState}
             matchLog={matchLog}
             playerName={this.playerName()}
+            debugPlayer={this.debugPlayer()}
           />
         </div>
       </div>
     );
   }
 
+  // In a joined match, player 1 is our own bot, so we get to see its debug
+  // output.
+  public debugPlayer(): number | undefined {
+    if (this.props.match.type === M.MatchType.joined) {
+      return 1;
+    }
+    return undefined;
+  }
+
   public playerName() {
     const { match } = this.props;
     const playerNames: { [playerNum: number]: string } = {};
@@ -246,10 +256,11 @@ interface MatchDisplayProps {
   viewState: ViewState;
   matchLog: MatchLog;
   playerName: (playerNum: number) => string;
+  debugPlayer?: number;
 }
 
 const MatchDisplay: React.SFC<MatchDisplayProps> = (props) => {
-  const { viewState, matchLog, playerName } = props;
+  const { viewState, matchLog, playerName, debugPlayer } = props;
   switch (viewState) {
     case ViewState.VISUALIZER:
       // TODO This cast seems to be some type error related to importing the
@@ -260,6 +271,7 @@ const MatchDisplay: React.SFC<MatchDisplayProps> = (props) => {
           matchLog={matchLog as any}
           // TODO This is an ugly hack
           assetPrefix={Config.visualizerAssets}
+          debugPlayer={debugPlayer}
         />);
     case ViewState.LOG:
       return <LogView playerName={playerName} matchLog={matchLog} />;
@@ -0,0 +1,85 @@
+//! The planet wars map format, as read by the game server and written by the
+//! map tools.
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Map {
+    pub planets: Vec<Planet>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Planet {
+    pub name: String,
+    pub x: f64,
+    pub y: f64,
+    /// The number of the player that starts on this planet, if any.
+    /// Players are numbered from 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<u64>,
+    pub ship_count: u64,
+    /// Ships this planet builds every turn while it is owned by a player.
+    #[serde(default = "default_growth_rate")]
+    pub growth_rate: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>,
+}
+
+fn default_growth_rate() -> u64 {
+    1
+}
+
+impl Map {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Map> {
+        let mut file = File::open(path)?;
+        let mut buf = String::new();
+        file.read_to_string(&mut buf)?;
+        let map = serde_json::from_str(&buf)?;
+        Ok(map)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", self)
+    }
+
+    /// The number of players this map is made for: the highest owner that
+    /// occurs in it.
+    pub fn num_seats(&self) -> u64 {
+        self.planets.iter()
+            .filter_map(|planet| planet.owner)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The start planets of given seat.
+    pub fn starts(&self, seat: u64) -> Vec<&Planet> {
+        self.planets.iter()
+            .filter(|planet| planet.owner == Some(seat))
+            .collect()
+    }
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = serde_json::to_string_pretty(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}", value)
+    }
+}
+
+impl Planet {
+    /// The euclidean distance between two planets.
+    pub fn distance(&self, other: &Planet) -> f64 {
+        let dx = self.x - other.x;
+        let dy = self.y - other.y;
+        (dx.powi(2) + dy.powi(2)).sqrt()
+    }
+}
@@ -0,0 +1,40 @@
+extern crate map_model;
+extern crate serde_json;
+
+use std::fs;
+
+use map_model::Map;
+
+#[test]
+fn repository_maps_round_trip() {
+    for entry in fs::read_dir("../maps").unwrap() {
+        let path = entry.unwrap().path();
+        let map = Map::read(&path).unwrap();
+        let written = map.to_string();
+        let read: Map = serde_json::from_str(&written).unwrap();
+        assert_eq!(read, map, "{}", path.display());
+    }
+}
+
+#[test]
+fn optional_fields() {
+    let map: Map = serde_json::from_str(r#"{
+        "planets": [
+            { "name": "protos", "x": 0, "y": 0, "owner": 1, "ship_count": 6 },
+            { "name": "duteros", "x": 3, "y": 4, "ship_count": 2,
+              "growth_rate": 3, "radius": 1.5 }
+        ]
+    }"#).unwrap();
+
+    assert_eq!(map.num_seats(), 1);
+    assert_eq!(map.planets[0].growth_rate, 1);
+    assert_eq!(map.planets[0].radius, None);
+    assert_eq!(map.planets[1].owner, None);
+    assert_eq!(map.planets[1].growth_rate, 3);
+    assert_eq!(map.planets[0].distance(&map.planets[1]), 5.0);
+
+    // absent fields stay absent
+    let written: serde_json::Value = serde_json::from_str(&map.to_string()).unwrap();
+    assert!(written["planets"][0].get("radius").is_none());
+    assert!(written["planets"][1].get("owner").is_none());
+}
@@ -18,6 +18,16 @@ export abstract class MatchLog {
     return this.gameStates[this.gameStates.length - 1].livingPlayers();
   }
 
+  // The debug info a player sent along with its turns, indexed like
+  // gameStates. Other players' debug info is left out.
+  public playerDebug(playerNum: number): Array<PwTypes.DebugInfo | undefined> {
+    const playerLog = this.playerLogs[playerNum];
+    if (!playerLog) {
+      return [];
+    }
+    return playerLog.turns.map((turn) => turn.debug);
+  }
+
   public abstract addEntry(entry: PwTypes.LogEntry): void;
 
   protected getPlayerLog(playerNum: number) {
@@ -131,6 +141,10 @@ export class PlayerLog {
         this.turns[this.turns.length - 1].action = record.action;
         break;
       }
+      case 'debug': {
+        this.turns[this.turns.length - 1].debug = record.debug;
+        break;
+      }
     }
   }
 }
@@ -139,6 +153,7 @@ export interface PlayerTurn {
   state: PwTypes.GameState;
   command?: string;
   action?: PwTypes.PlayerAction;
+  debug?: PwTypes.DebugInfo;
 }
 
 export interface PlayerMap<T> {
@@ -26,6 +26,19 @@ export interface Command {
   "ship_count": number;
 }
 
+/**
+* Diagnostics a bot can send along with its moves. Only its owner sees them.
+*/
+export interface DebugInfo {
+  "messages"?: string[];
+  "annotations"?: Annotation[];
+}
+
+export interface Annotation {
+  "planet": string;
+  "label": string;
+}
+
 /**
 * A PlayerAction describes the action a player took in his turn
 */
@@ -58,7 +71,10 @@ export type DispatchError
   | 'OriginNotOwned'
   | 'ZeroShipMove'
   | 'OriginDoesNotExist'
-  | 'DestinationDoesNotExist';
+  | 'DestinationDoesNotExist'
+  | 'SelfTarget'
+  | 'DuplicateCommand'
+  | 'Overdrawn';
 
 
 /**
@@ -108,7 +124,8 @@ export interface PlayerLogEntry {
 export type LogRecord
   = StepRecord
   | CommandRecord
-  | PlayerActionRecord;
+  | PlayerActionRecord
+  | DebugRecord;
 
 
 export interface StepRecord {
@@ -125,4 +142,9 @@ export interface CommandRecord {
 export interface PlayerActionRecord {
   "type": "player_action";
   "action": PlayerAction;
-}
\ No newline at end of file
+}
+
+export interface DebugRecord {
+  "type": "debug";
+  "debug": DebugInfo;
+}
@@ -74,6 +74,8 @@ export interface JsonPlanet {
   "y": number;
   "owner": number;
   "name": string;
+  "growth_rate"?: number;
+  "radius"?: number;
 }
 
 export function isJsonPlanet(obj: any): obj is JsonPlanet {
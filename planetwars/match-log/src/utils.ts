@@ -14,6 +14,16 @@ export function logFileEntries(logFileContent: string): PwTypes.LogEntry[] {
   return logFileContent.trim().split('\n').map((line: string) => JSON.parse(line));
 }
 
+// Leave out the debug records of all players but one, for handing a log to
+// that player's owner.
+export function ownerEntries(entries: PwTypes.LogEntry[], playerNum: number): PwTypes.LogEntry[] {
+  return entries.filter((entry) => {
+    return entry.type !== 'player_entry'
+      || entry.player === playerNum
+      || entry.record.type !== 'debug';
+  });
+}
+
 export function parseLog(logFileContent: string, type: MatchType): MatchLog {
   const log = emptyLog(type)
   logFileEntries(logFileContent).forEach((entry) => {
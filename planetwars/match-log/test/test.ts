@@ -31,6 +31,36 @@ describe('small log contains correct', () => {
   });
 });
 
+describe('debug info', () => {
+  const state = { planets: [], expeditions: [] };
+  const entries: M.PwTypes.LogEntry[] = [];
+  [1, 2].forEach((player) => {
+    const records: M.PwTypes.LogRecord[] = [
+      { type: 'step', turn_number: 1, state },
+      { type: 'command', content: '{"moves":[]}' },
+      { type: 'debug', debug: { messages: [`player ${player}`] } },
+    ];
+    records.forEach((record) => {
+      entries.push({ type: 'player_entry', player, record });
+    });
+  });
+
+  test('is read per player', () => {
+    const log = M.emptyLog(M.MatchType.joined);
+    entries.forEach((entry) => log.addEntry(entry));
+    expect(log.playerDebug(1)).toEqual([{ messages: ['player 1'] }]);
+    expect(log.playerDebug(2)).toEqual([{ messages: ['player 2'] }]);
+    expect(log.playerDebug(3)).toEqual([]);
+  });
+
+  test('of other players is left out for an owner', () => {
+    const log = M.emptyLog(M.MatchType.joined);
+    M.ownerEntries(entries, 1).forEach((entry) => log.addEntry(entry));
+    expect(log.playerDebug(1)).toEqual([{ messages: ['player 1'] }]);
+    expect(log.playerDebug(2)).toEqual([undefined]);
+  });
+});
+
 export function parseLogFile(path: string, type: M.MatchType): M.MatchLog {
   const content = fs.readFileSync(path, 'utf-8');
   return M.parseLog(content, type);
@@ -0,0 +1,608 @@
+use std::sync::Arc;
+
+use rules::{Rules, CombatModel};
+
+/// Identifies a player. Player ids count from 0, unlike the player numbers
+/// in the protocol, which count from 1.
+#[derive(PartialEq, Clone, Copy, Eq, Hash, Serialize, Deserialize, Debug)]
+pub struct PlayerId {
+    id: usize,
+}
+
+impl PlayerId {
+    pub fn new(id: usize) -> PlayerId {
+        PlayerId {
+            id
+        }
+    }
+
+    pub fn as_usize(&self) -> usize {
+        self.id
+    }
+}
+
+/// The state of a planet wars game, and the rules that advance it.
+///
+/// A turn is played in three steps, the way the server does it:
+/// `repopulate` lets planets build ships, `dispatch` sends out the
+/// expeditions the players ordered, and `step` moves the expeditions and
+/// resolves combat. `play_turn` does all three.
+///
+/// Cloning a game is cheap: the rules and planet names are shared between
+/// the clones.
+#[derive(Clone)]
+pub struct PlanetWars {
+    pub players: Vec<Player>,
+    pub planets: Vec<Planet>,
+    pub expeditions: Vec<Expedition>,
+    // How many expeditions were already dispatched.
+    // This is needed for assigning expedition identifiers.
+    pub expedition_num: u64,
+    pub turn_num: u64,
+    pub max_turns: u64,
+    pub rules: Arc<Rules>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Player {
+    pub id: PlayerId,
+    pub alive: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Fleet {
+    pub owner: Option<PlayerId>,
+    pub ship_count: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Planet {
+    pub id: usize,
+    pub name: Arc<str>,
+    pub fleets: Vec<Fleet>,
+    pub x: f64,
+    pub y: f64,
+    pub growth_rate: u64,
+    pub radius: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expedition {
+    pub id: u64,
+    pub origin: usize,
+    pub target: usize,
+    pub fleet: Fleet,
+    pub turns_remaining: u64,
+}
+
+/// An order to send ships from a planet the sender owns to another planet.
+#[derive(Debug)]
+pub struct Dispatch {
+    pub origin: usize,
+    pub target: usize,
+    pub ship_count: u64,
+}
+
+impl PlanetWars {
+    /// Look up a planet by its name.
+    pub fn planet_id(&self, name: &str) -> Option<usize> {
+        self.planets.iter().position(|planet| &*planet.name == name)
+    }
+
+    /// Play a whole turn, in which given dispatches are sent out. The
+    /// dispatches are not validated; they should come from the planet's
+    /// owner, and send no more ships than it has after repopulating.
+    pub fn play_turn(&mut self, dispatches: &[Dispatch]) {
+        self.repopulate();
+        for dispatch in dispatches {
+            self.dispatch(dispatch);
+        }
+        self.step();
+    }
+
+    /// Send out an expedition.
+    pub fn dispatch(&mut self, dispatch: &Dispatch) {
+        let distance = self.rules.movement.travel_time(
+            &self.planets[dispatch.origin],
+            &self.planets[dispatch.target],
+            dispatch.ship_count,
+        );
+
+        let origin = &mut self.planets[dispatch.origin];
+        origin.fleets[0].ship_count -= dispatch.ship_count;
+
+
+        let expedition = Expedition {
+            id: self.expedition_num,
+            origin: dispatch.origin,
+            target: dispatch.target,
+            turns_remaining: distance,
+            fleet: Fleet {
+                owner: origin.owner(),
+                ship_count: dispatch.ship_count,
+            },
+        };
+
+        // increment counter
+        self.expedition_num += 1;
+        self.expeditions.push(expedition);
+    }
+
+    // Play one step of the game
+    pub fn step(&mut self) {
+        self.turn_num += 1;
+
+        // Initially mark all players dead, re-marking them as alive once we
+        // encounter a sign of life.
+        for player in self.players.iter_mut() {
+            player.alive = false;
+        }
+
+        // Remember which planets had a fleet present before any expedition
+        // arrived; that fleet is defending the planet.
+        let defended: Vec<bool> = self.planets.iter()
+            .map(|planet| !planet.fleets.is_empty())
+            .collect();
+
+        self.step_expeditions();
+        self.resolve_combat(&defended);
+    }
+
+    /// Let every owned planet build ships, and neutral planets too when the
+    /// rules say so.
+    pub fn repopulate(&mut self) {
+        for planet in self.planets.iter_mut() {
+            if planet.owner().is_some() {
+                planet.fleets[0].ship_count += planet.growth_rate;
+            } else if self.rules.neutrals.growth {
+                let garrison = planet.ship_count();
+                let grown = self.rules.neutrals.cap(garrison + planet.growth_rate);
+                // a garrison above the cap does not shrink
+                if grown > garrison {
+                    match planet.fleets.first_mut() {
+                        Some(fleet) => fleet.ship_count = grown,
+                        None => planet.fleets.push(Fleet {
+                            owner: None,
+                            ship_count: grown,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+
+    fn step_expeditions(&mut self) {
+        let mut i = 0;
+        let exps = &mut self.expeditions;
+        while i < exps.len() {
+            // compare with 1 to avoid issues with planet distance 0
+            if exps[i].turns_remaining <= 1 {
+                // remove expedition from expeditions, and add to fleet
+                let exp = exps.swap_remove(i);
+                let planet = &mut self.planets[exp.target];
+                planet.orbit(exp.fleet);
+            } else {
+                exps[i].turns_remaining -= 1;
+                if let Some(owner_id) = exps[i].fleet.owner {
+                    // owner has an expedition in progress; this is a sign of life.
+                    self.players[owner_id.as_usize()].alive = true;
+                }
+
+                // proceed to next expedition
+                i += 1;
+            }
+        }
+    }
+
+    fn resolve_combat(&mut self, defended: &[bool]) {
+        for planet in self.planets.iter_mut() {
+            planet.resolve_combat(self.rules.combat, defended[planet.id]);
+            if let Some(owner_id) = planet.owner() {
+                // owner owns a planet; this is a sign of life.
+                self.players[owner_id.as_usize()].alive = true;
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        let remaining = self.players.iter().filter(|p| p.alive).count();
+        remaining < 2 || self.turn_num >= self.max_turns
+    }
+
+    /// Take a player out of the game. Its planets become neutral, keeping
+    /// their ships up to the neutral cap, and its expeditions disappear.
+    pub fn forfeit(&mut self, player_id: PlayerId) {
+        self.players[player_id.as_usize()].alive = false;
+        let neutrals = self.rules.neutrals;
+        for planet in self.planets.iter_mut() {
+            for fleet in planet.fleets.iter_mut() {
+                if fleet.owner == Some(player_id) {
+                    fleet.owner = None;
+                    fleet.ship_count = neutrals.cap(fleet.ship_count);
+                }
+            }
+            // only owned planets can hold an empty fleet
+            planet.fleets.retain(|fleet| {
+                fleet.owner.is_some() || fleet.ship_count > 0
+            });
+        }
+        self.expeditions.retain(|exp| exp.fleet.owner != Some(player_id));
+    }
+
+    pub fn living_players(&self) -> Vec<PlayerId> {
+        self.players.iter().filter_map(|p| {
+            if p.alive {
+                Some(p.id)
+            } else {
+                None
+            }
+        }).collect()
+    }
+}
+
+
+impl Planet {
+    pub fn owner(&self) -> Option<PlayerId> {
+        self.fleets.first().and_then(|f| f.owner)
+    }
+
+    pub fn ship_count(&self) -> u64 {
+        self.fleets.first().map_or(0, |f| f.ship_count)
+    }
+
+    /// Make a fleet orbit this planet.
+    pub fn orbit(&mut self, fleet: Fleet) {
+        // If owner already has a fleet present, merge
+        for other in self.fleets.iter_mut() {
+            if other.owner == fleet.owner {
+                other.ship_count += fleet.ship_count;
+                return;
+            }
+        }
+        // else, add fleet to fleets list
+        self.fleets.push(fleet);
+    }
+
+    /// Resolve combat between the fleets orbiting this planet. When
+    /// `defended` is set, the first fleet was present before any expedition
+    /// arrived.
+    pub fn resolve_combat(&mut self, model: CombatModel, defended: bool) {
+        if self.fleets.len() < 2 {
+            return;
+        }
+
+        match model {
+            CombatModel::Attrition => {
+                self.eliminate(|_| 1, |ships, enemy| ships - enemy);
+            }
+            CombatModel::DefenderBonus { bonus } => {
+                let defender = if defended {
+                    Some(self.fleets[0].owner)
+                } else {
+                    None
+                };
+                // strength of a single ship, in percent
+                self.eliminate(
+                    |owner| {
+                        if Some(owner) == defender {
+                            100 + u128::from(bonus)
+                        } else {
+                            100
+                        }
+                    },
+                    |strength, enemy| strength - enemy,
+                );
+            }
+            CombatModel::Proportional => {
+                self.eliminate(|_| 1, |ships, enemy| {
+                    isqrt(ships * ships - enemy * enemy)
+                });
+            }
+            CombatModel::Classic => {
+                let original_owner = if defended {
+                    self.fleets[0].owner
+                } else {
+                    None
+                };
+
+                self.fleets.sort_by(|a, b| a.ship_count.cmp(&b.ship_count).reverse());
+                let remaining = self.fleets[0].ship_count - self.fleets[1].ship_count;
+                if remaining > 0 {
+                    self.fleets.truncate(1);
+                    self.fleets[0].ship_count = remaining;
+                } else {
+                    self.fleets.clear();
+                    if original_owner.is_some() {
+                        self.fleets.push(Fleet {
+                            owner: original_owner,
+                            ship_count: 0,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Let the weakest fleet fight all other fleets, until at most one fleet
+    /// remains. Every ship of a fleet is worth `per_ship(owner)`, and
+    /// `fight(strength, enemy)` gives the strength a fleet has left after
+    /// fighting an enemy fleet that is not stronger. Strengths are kept in
+    /// u128, so that squaring or scaling a u64 ship count cannot overflow.
+    fn eliminate<S, F>(&mut self, per_ship: S, fight: F)
+        where S: Fn(Option<PlayerId>) -> u128,
+              F: Fn(u128, u128) -> u128
+    {
+        let mut fleets: Vec<(Option<PlayerId>, u128)> = self.fleets.iter()
+            .map(|fleet| {
+                let strength = u128::from(fleet.ship_count)
+                    .saturating_mul(per_ship(fleet.owner));
+                (fleet.owner, strength)
+            })
+            .collect();
+
+        // The player owning the strongest fleet present will win the combat.
+        // Here, we resolve how strong his fleet will be afterwards.
+        fleets.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+        while fleets.len() > 1 {
+            let (_, enemy) = fleets.pop().unwrap();
+            // destroy some ships
+            for other in fleets.iter_mut() {
+                other.1 = fight(other.1, enemy);
+            }
+
+            // remove dead fleets
+            while fleets.last().map(|&(_, strength)| strength) == Some(0) {
+                fleets.pop();
+            }
+        }
+
+        // partially destroyed ships survive; this never exceeds the ship
+        // count the fleet started with, so it fits in a u64 again
+        self.fleets = fleets.into_iter().map(|(owner, strength)| Fleet {
+            owner,
+            ship_count: strength.div_ceil(per_ship(owner)) as u64,
+        }).collect();
+    }
+}
+
+/// Integer square root, rounded down.
+fn isqrt(n: u128) -> u128 {
+    let mut root = (n as f64).sqrt() as u128;
+    // correct for floating point errors; dividing instead of squaring the
+    // root cannot overflow
+    while root > 0 && root > n / root {
+        root -= 1;
+    }
+    while root < n / (root + 1) {
+        root += 1;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rules::{DistanceMetric, Movement, Neutrals, SpeedBracket};
+
+    fn planet(x: f64, y: f64) -> Planet {
+        Planet {
+            id: 0,
+            name: "protos".into(),
+            fleets: Vec::new(),
+            x,
+            y,
+            growth_rate: 1,
+            radius: None,
+        }
+    }
+
+    fn fleet(owner: usize, ship_count: u64) -> Fleet {
+        Fleet {
+            owner: Some(PlayerId::new(owner)),
+            ship_count,
+        }
+    }
+
+    /// A game at its first turn, in which all players are alive.
+    fn game(num_players: usize, planets: Vec<Planet>, rules: Rules) -> PlanetWars {
+        PlanetWars {
+            players: (0..num_players)
+                .map(|id| Player { id: PlayerId::new(id), alive: true })
+                .collect(),
+            planets,
+            expeditions: Vec::new(),
+            expedition_num: 0,
+            turn_num: 0,
+            max_turns: 10,
+            rules: Arc::new(rules),
+        }
+    }
+
+    /// Resolve combat between given fleets, the first of which defends.
+    fn fight(model: CombatModel, fleets: Vec<Fleet>) -> Vec<(Option<usize>, u64)> {
+        let mut planet = planet(0.0, 0.0);
+        planet.fleets = fleets;
+        planet.resolve_combat(model, true);
+        planet.fleets.iter()
+            .map(|f| (f.owner.map(|id| id.as_usize()), f.ship_count))
+            .collect()
+    }
+
+    #[test]
+    fn attrition() {
+        let model = CombatModel::Attrition;
+        assert_eq!(fight(model, vec![fleet(0, 10), fleet(1, 5)]), vec![(Some(0), 5)]);
+        assert_eq!(fight(model, vec![fleet(0, 5), fleet(1, 10)]), vec![(Some(1), 5)]);
+        assert_eq!(fight(model, vec![fleet(0, 7), fleet(1, 7)]), vec![]);
+        assert_eq!(
+            fight(model, vec![fleet(0, 10), fleet(1, 8), fleet(2, 3)]),
+            vec![(Some(0), 2)]
+        );
+    }
+
+    #[test]
+    fn defender_bonus() {
+        let model = CombatModel::DefenderBonus { bonus: 50 };
+        // 10 defenders are worth 15 attackers
+        assert_eq!(fight(model, vec![fleet(0, 10), fleet(1, 12)]), vec![(Some(0), 2)]);
+        assert_eq!(fight(model, vec![fleet(0, 10), fleet(1, 14)]), vec![(Some(0), 1)]);
+        assert_eq!(fight(model, vec![fleet(0, 10), fleet(1, 15)]), vec![]);
+        assert_eq!(fight(model, vec![fleet(0, 10), fleet(1, 20)]), vec![(Some(1), 5)]);
+        // the bonus only applies to the defending fleet
+        assert_eq!(
+            fight(model, vec![fleet(0, 2), fleet(1, 10), fleet(2, 6)]),
+            vec![(Some(1), 4)]
+        );
+    }
+
+    #[test]
+    fn proportional() {
+        let model = CombatModel::Proportional;
+        assert_eq!(fight(model, vec![fleet(0, 10), fleet(1, 6)]), vec![(Some(0), 8)]);
+        assert_eq!(fight(model, vec![fleet(0, 9), fleet(1, 10)]), vec![(Some(1), 4)]);
+        assert_eq!(fight(model, vec![fleet(0, 4), fleet(1, 4)]), vec![]);
+        assert_eq!(
+            fight(model, vec![fleet(0, 8), fleet(1, 10), fleet(2, 8)]),
+            vec![(Some(1), 6)]
+        );
+    }
+
+    #[test]
+    fn large_fleets() {
+        let max = u64::MAX;
+        assert_eq!(
+            fight(CombatModel::Attrition, vec![fleet(0, max), fleet(1, max - 1)]),
+            vec![(Some(0), 1)]
+        );
+        // 2^62 defenders are worth 1.5 * 2^62 attackers
+        let model = CombatModel::DefenderBonus { bonus: 50 };
+        assert_eq!(
+            fight(model, vec![fleet(0, 1 << 62), fleet(1, 1 << 62)]),
+            vec![(Some(0), 1_537_228_672_809_129_302)]
+        );
+        assert_eq!(
+            fight(model, vec![fleet(0, max), fleet(1, max)]),
+            vec![(Some(0), 6_148_914_691_236_517_205)]
+        );
+        // strengths beyond u128 saturate, but the defender still wins
+        let model = CombatModel::DefenderBonus { bonus: max };
+        let survivors = fight(model, vec![fleet(0, max), fleet(1, max)]);
+        assert_eq!(survivors.len(), 1);
+        assert_eq!(survivors[0].0, Some(0));
+        let model = CombatModel::Proportional;
+        assert_eq!(
+            fight(model, vec![fleet(0, 5 << 40), fleet(1, 4 << 40)]),
+            vec![(Some(0), 3 << 40)]
+        );
+        assert_eq!(
+            fight(model, vec![fleet(0, max), fleet(1, 1)]),
+            vec![(Some(0), max - 1)]
+        );
+    }
+
+    #[test]
+    fn classic() {
+        let model = CombatModel::Classic;
+        assert_eq!(fight(model, vec![fleet(0, 10), fleet(1, 4)]), vec![(Some(0), 6)]);
+        assert_eq!(
+            fight(model, vec![fleet(0, 3), fleet(1, 10), fleet(2, 8)]),
+            vec![(Some(1), 2)]
+        );
+        // ties leave the planet with its original owner
+        assert_eq!(fight(model, vec![fleet(0, 5), fleet(1, 5)]), vec![(Some(0), 0)]);
+        assert_eq!(
+            fight(model, vec![fleet(0, 2), fleet(1, 6), fleet(2, 6)]),
+            vec![(Some(0), 0)]
+        );
+        let neutral = Fleet { owner: None, ship_count: 4 };
+        assert_eq!(fight(model, vec![neutral, fleet(1, 4)]), vec![]);
+    }
+
+    #[test]
+    fn travel_times() {
+        let (a, b) = (planet(0.0, 0.0), planet(3.0, -4.0));
+        let mut movement = Movement::default();
+        assert_eq!(movement.travel_time(&a, &b, 1), 5);
+        movement.metric = DistanceMetric::Manhattan;
+        assert_eq!(movement.travel_time(&a, &b, 1), 7);
+        movement.metric = DistanceMetric::Hex;
+        assert_eq!(movement.travel_time(&a, &b, 1), 4);
+
+        movement.speeds = vec![
+            SpeedBracket { min_ships: 10, speed: 1.0 },
+            SpeedBracket { min_ships: 2, speed: 1.5 },
+        ];
+        assert_eq!(movement.travel_time(&a, &b, 1), 3);
+        assert_eq!(movement.travel_time(&a, &b, 9), 3);
+        assert_eq!(movement.travel_time(&a, &b, 10), 4);
+    }
+
+    #[test]
+    fn neutral_growth() {
+        let mut planets = vec![
+            planet(0.0, 0.0),
+            planet(1.0, 0.0),
+            planet(2.0, 0.0),
+            planet(3.0, 0.0),
+        ];
+        planets[0].fleets.push(Fleet { owner: None, ship_count: 4 });
+        planets[1].fleets.push(Fleet { owner: None, ship_count: 9 });
+        planets[2].fleets.push(fleet(0, 4));
+        // an empty neutral planet grows as well
+        let mut state = game(1, planets, Rules {
+            neutrals: Neutrals { growth: true, max_ships: Some(5) },
+            ..Rules::default()
+        });
+
+        state.repopulate();
+        state.repopulate();
+        let ship_counts: Vec<u64> = state.planets.iter()
+            .map(|planet| planet.ship_count())
+            .collect();
+        // garrisons that are already above the cap stay as they are
+        assert_eq!(ship_counts, vec![5, 9, 6, 2]);
+        assert_eq!(state.planets[3].owner(), None);
+    }
+
+    #[test]
+    fn forfeit() {
+        let mut planets = vec![planet(0.0, 0.0), planet(1.0, 0.0)];
+        planets[0].fleets.push(fleet(0, 4));
+        planets[1].fleets.push(fleet(1, 0));
+        let mut state = game(2, planets, Rules::default());
+        state.dispatch(&Dispatch { origin: 0, target: 1, ship_count: 1 });
+
+        state.forfeit(PlayerId::new(0));
+        state.forfeit(PlayerId::new(1));
+        assert!(state.living_players().is_empty());
+        assert_eq!(state.planets[0].owner(), None);
+        assert_eq!(state.planets[0].ship_count(), 3);
+        assert!(state.planets[1].fleets.is_empty());
+        assert!(state.expeditions.is_empty());
+    }
+
+    #[test]
+    fn forfeited_planets_are_capped() {
+        let mut planets = vec![planet(0.0, 0.0), planet(1.0, 0.0)];
+        planets[0].fleets.push(fleet(0, 8));
+        planets[1].fleets.push(fleet(1, 3));
+        let mut state = game(2, planets, Rules {
+            neutrals: Neutrals { growth: false, max_ships: Some(5) },
+            ..Rules::default()
+        });
+
+        state.forfeit(PlayerId::new(0));
+        assert_eq!(state.planets[0].owner(), None);
+        assert_eq!(state.planets[0].ship_count(), 5);
+        assert_eq!(state.planets[1].ship_count(), 3);
+    }
+
+    #[test]
+    fn integer_square_root() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(35), 5);
+        assert_eq!(isqrt(36), 6);
+        assert_eq!(isqrt(1 << 60), 1 << 30);
+        assert_eq!(isqrt(u128::MAX), u128::from(u64::MAX));
+    }
+}
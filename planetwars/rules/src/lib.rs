@@ -0,0 +1,45 @@
+//! The planet wars game rules, exactly as the game server plays them.
+//!
+//! A game can be built from a state in protocol format, as received by a
+//! bot or found in a match log, and then be played forward with any
+//! dispatches, for example to look ahead in a bot or to analyse a match:
+//!
+//! ```
+//! # extern crate planetwars_rules;
+//! # extern crate serde_json;
+//! use planetwars_rules::{PlanetWars, PlayerId, Rules, Dispatch};
+//! use planetwars_rules::protocol::State;
+//!
+//! # fn main() {
+//! let state: State = serde_json::from_str(r#"{
+//!     "max_turns": 100,
+//!     "planets": [
+//!         { "name": "protos", "x": 0, "y": 0, "owner": 1, "ship_count": 10 },
+//!         { "name": "duteros", "x": 3, "y": 0, "owner": 2, "ship_count": 5 }
+//!     ],
+//!     "expeditions": []
+//! }"#).unwrap();
+//!
+//! let game = PlanetWars::from_state(&state, Rules::default()).unwrap();
+//!
+//! // what happens when we attack duteros, which is 3 turns away
+//! let mut future = game.clone();
+//! future.play_turn(&[Dispatch { origin: 0, target: 1, ship_count: 9 }]);
+//! future.play_turn(&[]);
+//! future.play_turn(&[]);
+//! assert_eq!(future.planets[1].owner(), Some(PlayerId::new(0)));
+//! assert_eq!(game.planets[1].owner(), Some(PlayerId::new(1)));
+//! # }
+//! ```
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+mod game;
+mod rules;
+pub mod protocol;
+
+pub use game::{PlanetWars, PlayerId, Player, Planet, Fleet, Expedition, Dispatch};
+pub use rules::{Rules, CombatModel, Neutrals, DistanceMetric, SpeedBracket, Movement};
+pub use protocol::StateError;
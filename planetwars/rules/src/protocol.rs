@@ -0,0 +1,317 @@
+//! The messages the server and bots exchange, with the game state as the
+//! server sends it to bots and writes it to match logs, and the conversion
+//! of that state to a game that can be simulated.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use game;
+use game::{PlanetWars, PlayerId, Fleet};
+use rules::Rules;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Expedition {
+    pub id: u64,
+    pub ship_count: u64,
+    pub origin: String,
+    /// Absent for enemy expeditions when destinations are hidden.
+    pub destination: Option<String>,
+    pub owner: u64,
+    pub turns_remaining: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Planet {
+    pub ship_count: u64,
+    pub x: f64,
+    pub y: f64,
+    pub owner: Option<u64>,
+    pub name: String,
+    /// Ships this planet builds every turn while it is owned by a player.
+    #[serde(default = "default_growth_rate")]
+    pub growth_rate: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>,
+}
+
+fn default_growth_rate() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct State {
+    /// The turn this state starts, counting from 0.
+    #[serde(default)]
+    pub turn_num: u64,
+    /// The game ends once this many turns have been played.
+    #[serde(default)]
+    pub max_turns: u64,
+    #[serde(default)]
+    pub num_players: u64,
+    /// The player this state was sent to. As player numbers are rotated,
+    /// this is always 1. Only sent to players.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_id: Option<u64>,
+    /// Milliseconds left to respond to this state. Only sent to players,
+    /// with the states they are asked to respond to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_budget: Option<u64>,
+    pub planets: Vec<Planet>,
+    pub expeditions: Vec<Expedition>,
+    /// Only sent to players; omitted from logs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub travel_times: Vec<TravelTimes>,
+}
+
+impl State {
+    /// The number of turns that can still be played, including this one.
+    pub fn turns_left(&self) -> u64 {
+        self.max_turns.saturating_sub(self.turn_num)
+    }
+
+    /// The number of turns a fleet of given size takes to travel between
+    /// two planets, when the server sent travel times.
+    pub fn travel_time(&self, origin: &str, destination: &str, ship_count: u64)
+        -> Option<u64>
+    {
+        let times = self.travel_times.iter()
+            .rev()
+            .find(|times| times.min_ships <= ship_count)
+            .or_else(|| self.travel_times.first())?;
+        times.turns.get(origin)?.get(destination).cloned()
+    }
+}
+
+/// Travel times between all planets, for fleets of at least `min_ships`
+/// ships. The server sends them ordered by `min_ships`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TravelTimes {
+    pub min_ships: u64,
+    /// Turns it takes to travel, indexed by origin and then destination.
+    pub turns: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+/// The moves a bot makes in a turn, in response to a state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Action {
+    #[serde(rename = "moves")]
+    pub commands: Vec<Command>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug: Option<DebugInfo>,
+}
+
+impl Action {
+    pub fn new(commands: Vec<Command>) -> Self {
+        Action {
+            commands,
+            debug: None,
+        }
+    }
+}
+
+/// Diagnostics a bot can send along with its action. They are recorded in
+/// the match log, and never sent to other players.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DebugInfo {
+    #[serde(default)]
+    pub messages: Vec<String>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// A label attached to a planet, for example to mark a target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub planet: String,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Command {
+    pub origin: String,
+    pub destination: String,
+    pub ship_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CommandError {
+    NotEnoughShips,
+    OriginNotOwned,
+    ZeroShipMove,
+    OriginDoesNotExist,
+    DestinationDoesNotExist,
+    /// The origin is the destination.
+    SelfTarget,
+    /// The same move was sent earlier in the action.
+    DuplicateCommand,
+    /// Together with the other moves from its origin, the move sends more
+    /// ships than the planet has.
+    Overdrawn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerCommand {
+    pub command: Command,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CommandError>,
+}
+
+/// How the server took the action a bot sent in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "type", content = "value")]
+pub enum PlayerAction {
+    Timeout,
+    ParseError(String),
+    Commands(Vec<PlayerCommand>),
+}
+
+/// Who plays in a match, in the order of their player numbers. As player
+/// numbers are rotated, the bot it is sent to is always player 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameInfo {
+    pub players: Vec<PlayerInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerInfo {
+    pub name: String,
+    pub bot_version: String,
+    pub owner_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "type", content = "content")]
+pub enum ServerMessage {
+    /// Game state in current turn
+    GameState(State),
+    /// The action that was performed
+    PlayerAction(PlayerAction),
+    /// The game is over, and this is the concluding state.
+    FinalState(State),
+    /// Who plays in this match; sent before the first game state of every
+    /// round.
+    GameInfo(GameInfo),
+}
+
+/// Why a state could not be turned into a game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateError {
+    /// An expedition refers to a planet that is not in the state.
+    UnknownPlanet(String),
+    /// The destination of this expedition was hidden.
+    HiddenDestination(u64),
+    /// Players are numbered from 1.
+    InvalidOwner(u64),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::UnknownPlanet(ref name) => {
+                write!(f, "expedition refers to unknown planet {}", name)
+            }
+            StateError::HiddenDestination(id) => {
+                write!(f, "destination of expedition {} is hidden", id)
+            }
+            StateError::InvalidOwner(owner) => {
+                write!(f, "invalid player number {}", owner)
+            }
+        }
+    }
+}
+
+impl Error for StateError {
+    fn description(&self) -> &str {
+        "invalid game state"
+    }
+}
+
+impl PlanetWars {
+    /// Build a game from a state in protocol format, to be played with given
+    /// rules. Player numbers are kept as they are in the state, so in a
+    /// state sent to a bot, the bot is player 1.
+    pub fn from_state(state: &State, rules: Rules)
+        -> Result<PlanetWars, StateError>
+    {
+        let owners = state.planets.iter()
+            .filter_map(|planet| planet.owner)
+            .chain(state.expeditions.iter().map(|exp| exp.owner));
+        let mut num_players = state.num_players;
+        for owner in owners {
+            if owner == 0 {
+                return Err(StateError::InvalidOwner(owner));
+            }
+            num_players = num_players.max(owner);
+        }
+
+        let planets: Vec<game::Planet> = state.planets
+            .iter()
+            .enumerate()
+            .map(|(num, planet)| {
+                let mut fleets = Vec::new();
+                // owned planets can be left without ships after combat
+                if planet.ship_count > 0 || planet.owner.is_some() {
+                    fleets.push(Fleet {
+                        owner: planet.owner.map(player_id),
+                        ship_count: planet.ship_count,
+                    });
+                }
+                game::Planet {
+                    id: num,
+                    name: planet.name.as_str().into(),
+                    x: planet.x,
+                    y: planet.y,
+                    growth_rate: planet.growth_rate,
+                    radius: planet.radius,
+                    fleets,
+                }
+            }).collect();
+
+        let planet_num = |name: &str| {
+            planets.iter().position(|p| &*p.name == name)
+                .ok_or_else(|| StateError::UnknownPlanet(name.to_string()))
+        };
+        let mut expeditions = Vec::new();
+        for exp in state.expeditions.iter() {
+            let destination = exp.destination.as_ref()
+                .ok_or(StateError::HiddenDestination(exp.id))?;
+            expeditions.push(game::Expedition {
+                id: exp.id,
+                origin: planet_num(&exp.origin)?,
+                target: planet_num(destination)?,
+                turns_remaining: exp.turns_remaining,
+                fleet: Fleet {
+                    owner: Some(player_id(exp.owner)),
+                    ship_count: exp.ship_count,
+                },
+            });
+        }
+
+        let players = (0..num_players as usize)
+            .map(|id| game::Player { id: PlayerId::new(id), alive: true })
+            .collect();
+        let expedition_num = expeditions.iter()
+            .map(|exp| exp.id + 1)
+            .max()
+            .unwrap_or(0);
+
+        Ok(PlanetWars {
+            players,
+            planets,
+            expeditions,
+            expedition_num,
+            turn_num: state.turn_num,
+            max_turns: state.max_turns,
+            rules: Arc::new(rules),
+        })
+    }
+}
+
+/// The player id for a player number, which counts from 1.
+fn player_id(player_num: u64) -> PlayerId {
+    PlayerId::new(player_num as usize - 1)
+}
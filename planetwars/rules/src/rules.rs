@@ -0,0 +1,128 @@
+use game::Planet;
+
+/// The settings a match is played with. A game shares them between all of
+/// its clones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    #[serde(default)]
+    pub combat: CombatModel,
+    #[serde(default)]
+    pub movement: Movement,
+    #[serde(default)]
+    pub neutrals: Neutrals,
+}
+
+/// How combat on a planet is resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "type")]
+pub enum CombatModel {
+    /// Every ship destroys exactly one enemy ship.
+    #[default]
+    Attrition,
+    /// Like attrition, but the ships that were on the planet at the start of
+    /// the turn are worth `bonus` percent more. Partially destroyed
+    /// strength still counts as a whole ship.
+    DefenderBonus { bonus: u64 },
+    /// Lanchester's square law: a fleet of `a` ships that defeats a fleet of
+    /// `b` ships keeps `sqrt(a² - b²)` of them, rounded down.
+    Proportional,
+    /// The rule from the original Planet Wars: the largest fleet wins and
+    /// keeps as many ships as it outnumbers the second largest fleet with.
+    /// When the largest fleets are tied, the planet stays with its original
+    /// owner, without any ships.
+    Classic,
+}
+
+/// How planets held by neutral ships behave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Neutrals {
+    /// Whether neutral planets build ships, like player owned planets do.
+    #[serde(default)]
+    pub growth: bool,
+    /// The largest garrison a neutral planet can hold.
+    #[serde(default)]
+    pub max_ships: Option<u64>,
+}
+
+impl Neutrals {
+    /// Limit a neutral garrison to the cap, if there is one.
+    pub fn cap(&self, ship_count: u64) -> u64 {
+        self.max_ships.map_or(ship_count, |max| ship_count.min(max))
+    }
+}
+
+/// How distances between planets are measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    #[default]
+    Euclidean,
+    Manhattan,
+    /// Planet coordinates are axial hex coordinates; the distance is the
+    /// number of hexes to cross.
+    Hex,
+}
+
+impl DistanceMetric {
+    pub fn distance(&self, a: &Planet, b: &Planet) -> f64 {
+        let dx = a.x - b.x;
+        let dy = a.y - b.y;
+        match *self {
+            DistanceMetric::Euclidean => (dx.powi(2) + dy.powi(2)).sqrt(),
+            DistanceMetric::Manhattan => dx.abs() + dy.abs(),
+            DistanceMetric::Hex => (dx.abs() + dy.abs() + (dx + dy).abs()) / 2.0,
+        }
+    }
+}
+
+/// The speed of fleets that have at least `min_ships` ships.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpeedBracket {
+    pub min_ships: u64,
+    /// Distance travelled per turn; should be positive.
+    pub speed: f64,
+}
+
+/// Determines how long expeditions take to arrive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Movement {
+    #[serde(default)]
+    pub metric: DistanceMetric,
+    /// A fleet moves at the speed of the largest bracket it qualifies for.
+    /// Fleets that are too small for any bracket use the smallest one.
+    #[serde(default = "default_speeds")]
+    pub speeds: Vec<SpeedBracket>,
+}
+
+fn default_speeds() -> Vec<SpeedBracket> {
+    vec![SpeedBracket { min_ships: 0, speed: 1.0 }]
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Movement {
+            metric: DistanceMetric::default(),
+            speeds: default_speeds(),
+        }
+    }
+}
+
+impl Movement {
+    pub fn speed(&self, ship_count: u64) -> f64 {
+        let bracket = self.speeds.iter()
+            .filter(|bracket| bracket.min_ships <= ship_count)
+            .max_by_key(|bracket| bracket.min_ships)
+            .or_else(|| self.speeds.iter().min_by_key(|b| b.min_ships));
+        bracket.map_or(1.0, |bracket| bracket.speed)
+    }
+
+    /// The number of turns it takes a fleet of given size to travel between
+    /// two planets.
+    pub fn travel_time(&self, origin: &Planet, target: &Planet, ship_count: u64)
+        -> u64
+    {
+        let distance = self.metric.distance(origin, target);
+        (distance / self.speed(ship_count)).ceil() as u64
+    }
+}
@@ -0,0 +1,83 @@
+extern crate planetwars_rules;
+extern crate serde_json;
+
+use planetwars_rules::{PlanetWars, PlayerId, Rules, Dispatch, StateError};
+use planetwars_rules::protocol::State;
+
+const STATE: &str = r#"{
+    "turn_num": 4,
+    "max_turns": 10,
+    "num_players": 2,
+    "planets": [
+        { "name": "protos", "x": 0, "y": 0, "owner": 1, "ship_count": 10 },
+        { "name": "duteros", "x": 4, "y": 0, "owner": 2, "ship_count": 0 },
+        { "name": "tritos", "x": 0, "y": 3, "ship_count": 2 }
+    ],
+    "expeditions": [
+        { "id": 7, "ship_count": 5, "origin": "protos", "destination": "tritos",
+          "owner": 1, "turns_remaining": 1 }
+    ]
+}"#;
+
+fn state() -> State {
+    serde_json::from_str(STATE).unwrap()
+}
+
+#[test]
+fn game_from_state() {
+    let game = PlanetWars::from_state(&state(), Rules::default()).unwrap();
+    assert_eq!(game.players.len(), 2);
+    assert_eq!(game.turn_num, 4);
+    assert_eq!(game.max_turns, 10);
+    assert_eq!(game.planet_id("tritos"), Some(2));
+    // owned planets keep their owner, even without ships
+    assert_eq!(game.planets[1].owner(), Some(PlayerId::new(1)));
+    assert_eq!(game.planets[2].owner(), None);
+    assert_eq!(game.expeditions[0].target, 2);
+    // new expeditions get fresh ids
+    assert_eq!(game.expedition_num, 8);
+}
+
+#[test]
+fn playing_turns() {
+    let mut game = PlanetWars::from_state(&state(), Rules::default()).unwrap();
+    let before = game.clone();
+
+    game.play_turn(&[Dispatch { origin: 0, target: 1, ship_count: 11 }]);
+    assert_eq!(game.turn_num, 5);
+    // the expedition arrived, and took tritos
+    assert_eq!(game.planets[2].owner(), Some(PlayerId::new(0)));
+    assert_eq!(game.planets[2].ship_count(), 3);
+    assert_eq!(game.planets[0].ship_count(), 0);
+    assert_eq!(game.expeditions[0].id, 8);
+
+    // clones do not share their state
+    assert_eq!(before.turn_num, 4);
+    assert_eq!(before.planets[0].ship_count(), 10);
+}
+
+#[test]
+fn player_count_follows_owners() {
+    let mut state = state();
+    state.num_players = 0;
+    state.planets[2].owner = Some(3);
+    let game = PlanetWars::from_state(&state, Rules::default()).unwrap();
+    assert_eq!(game.players.len(), 3);
+}
+
+#[test]
+fn refused_states() {
+    let mut hidden = state();
+    hidden.expeditions[0].destination = None;
+    assert_eq!(
+        PlanetWars::from_state(&hidden, Rules::default()).err(),
+        Some(StateError::HiddenDestination(7)),
+    );
+
+    let mut unknown = state();
+    unknown.expeditions[0].origin = "pentos".to_string();
+    assert_eq!(
+        PlanetWars::from_state(&unknown, Rules::default()).err(),
+        Some(StateError::UnknownPlanet("pentos".to_string())),
+    );
+}
@@ -1,3 +1,5 @@
+use std::fmt::Display;
+
 use util::{Bound};
 
 const MIN_PLANETS: usize = 4;
@@ -14,12 +16,39 @@ const TOP_BOUND: isize = 20;
 const MAX_START_SHIPS: usize = 15;
 const MIN_START_SHIPS: usize = 1;
 
+const MIN_NEUTRALS: usize = 2;
+const MAX_NEUTRALS: usize = 5;
+
+const MIN_SPACING: f64 = 3.0;
+
+/// The symmetry of generated maps.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Symmetry {
+    /// Every player's part of the map is a rotation of the others.
+    Rotational,
+    /// The map is mirrored over its vertical center line. Only possible for
+    /// two players; other amounts fall back to rotational symmetry.
+    Mirror,
+}
+
+/// Parameters for map generation. All bounds exclude their maximum.
+/// When read from JSON, missing fields take their default value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+    /// Planets in the map, when it is not symmetric.
     pub planet_amount: Bound<usize>,
     pub player_amount: Bound<usize>,
     pub horizontal_bound: Bound<isize>,
     pub vertical_bound: Bound<isize>,
-    pub start_ships: Bound<usize>
+    pub start_ships: Bound<usize>,
+    /// Neutral planets per player, for symmetric maps.
+    pub neutral_amount: Bound<usize>,
+    /// Minimal distance between planets, for symmetric maps.
+    pub min_spacing: f64,
+    /// Maps without a symmetry have their planets spread at random.
+    pub symmetry: Option<Symmetry>,
 }
 
 impl Config {
@@ -30,6 +59,72 @@ impl Config {
             horizontal_bound: Bound { min: LEFT_BOUND, max: RIGHT_BOUND + 1},
             vertical_bound: Bound { min: BOT_BOUND, max: TOP_BOUND + 1},
             start_ships: Bound { min: MIN_START_SHIPS, max: MAX_START_SHIPS + 1},
+            neutral_amount: Bound { min: MIN_NEUTRALS, max: MAX_NEUTRALS + 1},
+            min_spacing: MIN_SPACING,
+            symmetry: None,
+        }
+    }
+
+    /// Check that maps can be generated from this config. Whether symmetric
+    /// maps have room for their start planets is only known once they are
+    /// generated.
+    pub fn validate(&self) -> Result<(), String> {
+        check_bound("planet_amount", &self.planet_amount)?;
+        check_bound("player_amount", &self.player_amount)?;
+        check_bound("horizontal_bound", &self.horizontal_bound)?;
+        check_bound("vertical_bound", &self.vertical_bound)?;
+        check_bound("start_ships", &self.start_ships)?;
+        check_bound("neutral_amount", &self.neutral_amount)?;
+
+        if self.player_amount.min == 0 {
+            return Err("player_amount must be at least 1".to_string());
         }
+        if !(self.min_spacing >= 0.0 && self.min_spacing.is_finite()) {
+            return Err(format!("invalid min_spacing: {}", self.min_spacing));
+        }
+        if self.symmetry.is_none() {
+            let max_players = self.player_amount.max - 1;
+            if self.planet_amount.min < max_players {
+                return Err(format!(
+                    "planet_amount leaves no planet for each of {} players",
+                    max_players,
+                ));
+            }
+            // planets are placed on distinct integer coordinates
+            let h = &self.horizontal_bound;
+            let v = &self.vertical_bound;
+            let spots = (h.max as f64 - h.min as f64)
+                * (v.max as f64 - v.min as f64);
+            let max_planets = self.planet_amount.max - 1;
+            if spots < max_planets as f64 {
+                return Err(format!(
+                    "{} planets do not fit in a map of {} spots",
+                    max_planets,
+                    spots,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
+
+fn check_bound<T: PartialOrd + Display>(name: &str, bound: &Bound<T>)
+    -> Result<(), String>
+{
+    if bound.min < bound.max {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} is empty: max {} is not larger than min {}",
+            name,
+            bound.max,
+            bound.min,
+        ))
     }
 }
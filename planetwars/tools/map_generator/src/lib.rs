@@ -6,13 +6,16 @@ extern crate num;
 extern crate serde_derive;
 extern crate serde_json;
 extern crate serde;
+extern crate map_model;
 
 
 mod config;
 mod util;
 mod map_generator;
-mod types;
+mod symmetric;
 
-pub use config::Config;
-pub use types::Map;
-pub use map_generator::create_map;
\ No newline at end of file
+pub use config::{Config, Symmetry};
+pub use map_model::Map;
+pub use util::Bound;
+pub use map_generator::{create_map, create_seeded_map, generate_map};
+pub use symmetric::create_symmetric_map;
\ No newline at end of file
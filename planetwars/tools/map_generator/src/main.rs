@@ -0,0 +1,145 @@
+extern crate map_generator;
+extern crate rand;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+
+use map_generator::{create_seeded_map, Config};
+
+const USAGE: &str = "\
+usage: map_generator [--config <file>] [--seed <n>] [--output <path>] [--count <n>]
+
+Generates a planet wars map. Without --count, the map is written to the
+output file, or to stdout. With --count, a pool of maps is written to the
+output directory, along with a manifest.json describing them.
+
+  --config <file>  JSON generator config; missing fields keep their default
+  --seed <n>       seed for the first map; random when absent
+  --output <path>  output file, or output directory with --count
+  --count <n>      the number of maps to generate; map i uses seed + i
+
+Every bound in the config is an object like {\"min\": 2, \"max\": 5}, which
+includes min and excludes max.";
+
+struct Args {
+    config: Option<String>,
+    seed: Option<u64>,
+    output: Option<String>,
+    count: Option<u64>,
+}
+
+/// Describes a generated map pool.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    config: &'a Config,
+    maps: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    file: String,
+    seed: u64,
+    players: u64,
+    planets: usize,
+}
+
+fn main() {
+    let args = match parse_args(env::args().skip(1).collect()) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(args) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        config: None,
+        seed: None,
+        output: None,
+        count: None,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(flag) = args.next() {
+        if !["--config", "--seed", "--output", "--count"].contains(&flag.as_str()) {
+            return Err(format!("unknown flag: {}", flag));
+        }
+        let value = args.next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        let number = |value: &str| {
+            value.parse::<u64>().map_err(|_| format!("invalid {}: {}", flag, value))
+        };
+        match flag.as_str() {
+            "--config" => parsed.config = Some(value),
+            "--seed" => parsed.seed = Some(number(&value)?),
+            "--output" => parsed.output = Some(value),
+            "--count" => parsed.count = Some(number(&value)?),
+            _ => unreachable!(),
+        }
+    }
+    Ok(parsed)
+}
+
+fn run(args: Args) -> Result<(), Box<Error>> {
+    let config = match args.config {
+        Some(ref path) => {
+            let mut buf = String::new();
+            File::open(path)?.read_to_string(&mut buf)?;
+            serde_json::from_str(&buf)?
+        }
+        None => Config::new(),
+    };
+    let seed = args.seed.unwrap_or_else(rand::random);
+
+    let count = match args.count {
+        None => {
+            let map = create_seeded_map(&config, seed)?;
+            eprintln!("seed: {}", seed);
+            match args.output {
+                Some(path) => map.write(path)?,
+                None => writeln!(io::stdout(), "{}", map)?,
+            }
+            return Ok(());
+        }
+        Some(count) => count,
+    };
+
+    let dir = args.output.ok_or("--count requires an output directory")?;
+    fs::create_dir_all(&dir)?;
+
+    let mut maps = Vec::new();
+    for i in 0..count {
+        let map_seed = seed.wrapping_add(i);
+        let map = create_seeded_map(&config, map_seed)?;
+        let file = format!("map_{}.json", i);
+        map.write(Path::new(&dir).join(&file))?;
+        maps.push(ManifestEntry {
+            file: file,
+            seed: map_seed,
+            players: map.num_seats(),
+            planets: map.planets.len(),
+        });
+    }
+
+    let manifest = Manifest {
+        config: &config,
+        maps: maps,
+    };
+    let file = File::create(Path::new(&dir).join("manifest.json"))?;
+    serde_json::to_writer_pretty(file, &manifest)?;
+    Ok(())
+}
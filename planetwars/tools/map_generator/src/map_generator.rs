@@ -1,52 +1,115 @@
 use itertools::multizip;
-use rand::{thread_rng, sample};
+use rand::{Rng, SeedableRng, Isaac64Rng, sample};
 
-use types::{Map, Planet};
+use map_model::{Map, Planet};
 use config::Config;
+use symmetric::create_symmetric_map;
 
-// TODO: Check invalid configs
-pub fn create_map(config: &Config) -> Map {
-    let num_players = config.player_amount.rand();
-    let num_planets = config.planet_amount.rand();
+/// Create a map from given seed. The same seed and config will always yield
+/// the same map. Fails when the config does not allow any map.
+pub fn create_seeded_map(config: &Config, seed: u64) -> Result<Map, String> {
+    let mut rng = Isaac64Rng::from_seed(&[seed]);
+    generate_map(config, &mut rng)
+}
+
+/// Create a map, symmetric when the config asks for it.
+pub fn generate_map<R: Rng>(config: &Config, rng: &mut R)
+    -> Result<Map, String>
+{
+    match config.symmetry {
+        Some(_) => create_symmetric_map(config, rng),
+        None => create_map(config, rng),
+    }
+}
+
+pub fn create_map<R: Rng>(config: &Config, rng: &mut R) -> Result<Map, String> {
+    config.validate()?;
+
+    let num_players = config.player_amount.rand(rng);
+    let num_planets = config.planet_amount.rand(rng);
 
     let horiz_space = config.horizontal_bound.range();
     let verti_space = config.vertical_bound.range();
     let eucli_space = iproduct!(horiz_space, verti_space);
-    let planet_locs = sample(&mut thread_rng(), eucli_space, num_planets);
+    let planet_locs = sample(rng, eucli_space, num_planets);
 
-    let ship_counts = config.start_ships.sample(num_planets);
+    let ship_counts = config.start_ships.sample(rng, num_planets);
 
-    let player_locs = sample(&mut thread_rng(), 0..num_planets, num_players);
-    let player_names = (0..num_players).map(|i| format!("Player_{}", i + 1));
+    let player_locs = sample(rng, 0..num_planets, num_players);
     let mut planet_owners = vec![None; planet_locs.len()];
-    player_locs.iter().enumerate().for_each(|(i, player)| {
-        planet_owners[i] = Some(format!("Player_{}", player))
+    player_locs.iter().enumerate().for_each(|(i, &planet)| {
+        planet_owners[planet] = Some(i as u64 + 1)
     });
 
     let planet_names = (0..num_planets).map(|i| format!("Planet_{}", i));
     let planet_tuples = multizip((planet_locs, ship_counts, planet_owners, planet_names));
     let planets = planet_tuples.map(|((x, y), ship_count, owner, name)| {
         Planet {
-            x: x as f32,
-            y: y as f32,
-            ship_count: ship_count,
+            x: x as f64,
+            y: y as f64,
+            ship_count: ship_count as u64,
             owner: owner,
-            name: name
+            name: name,
+            growth_rate: 1,
+            radius: None,
         }
     });
 
-    Map::new(player_names.collect(), planets.collect())
+    Ok(Map { planets: planets.collect() })
 }
 
 
 // Mostly for getting rid of warnings
 #[cfg(test)]
 mod test {
-    use super::create_map;
+    use rand::thread_rng;
+
+    use super::{create_map, create_seeded_map};
     use super::Config;
+    use config::Symmetry;
+    use util::Bound;
 
     #[test]
     fn creates_from_default() {
-        create_map(&Config::new());
+        create_map(&Config::new(), &mut thread_rng()).unwrap();
+    }
+
+    #[test]
+    fn every_player_gets_a_planet() {
+        let map = create_seeded_map(&Config::new(), 7).unwrap();
+        let mut owners: Vec<_> = map.planets.iter()
+            .filter_map(|planet| planet.owner)
+            .collect();
+        owners.sort();
+        assert_eq!(owners, vec![1, 2]);
+    }
+
+    #[test]
+    fn same_seed_same_map() {
+        let config = Config::new();
+        let map = create_seeded_map(&config, 42).unwrap().to_string();
+        assert_eq!(map, create_seeded_map(&config, 42).unwrap().to_string());
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let mut config = Config::new();
+        config.planet_amount = Bound { min: 5, max: 5 };
+        assert!(create_seeded_map(&config, 0).is_err());
+
+        let mut config = Config::new();
+        config.horizontal_bound = Bound { min: 0, max: 2 };
+        config.vertical_bound = Bound { min: 0, max: 2 };
+        assert!(create_seeded_map(&config, 0).is_err());
+
+        let mut config = Config::new();
+        config.player_amount = Bound { min: 5, max: 6 };
+        config.planet_amount = Bound { min: 4, max: 6 };
+        assert!(create_seeded_map(&config, 0).is_err());
+
+        let mut config = Config::new();
+        config.min_spacing = 1000.0;
+        config.symmetry = Some(Symmetry::Rotational);
+        assert!(create_seeded_map(&config, 0).is_err());
     }
 }
\ No newline at end of file
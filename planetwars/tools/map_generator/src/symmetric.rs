@@ -0,0 +1,219 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use config::{Config, Symmetry};
+use map_model::{Map, Planet};
+
+/// How often we try to find room for a planet before leaving it out.
+const PLACEMENT_ATTEMPTS: usize = 200;
+
+/// Create a map in which every player has an identical part of the map, so
+/// that no start position is better than another. Planets are at least
+/// `min_spacing` apart; neutral planets that do not fit are left out. Fails
+/// when there is no room for the start planets.
+pub fn create_symmetric_map<R: Rng>(config: &Config, rng: &mut R)
+    -> Result<Map, String>
+{
+    config.validate()?;
+    let num_players = config.player_amount.rand(rng);
+    let layout = Layout::new(config, num_players);
+
+    let mut orbits: Vec<(Vec<(f64, f64)>, usize)> = Vec::new();
+
+    let start = layout.place(&orbits, rng).ok_or_else(|| {
+        "no room for start planets; lower min_spacing or enlarge the map"
+            .to_string()
+    })?;
+    orbits.push((start, config.start_ships.rand(rng)));
+
+    let num_neutrals = config.neutral_amount.rand(rng);
+    for _ in 0..num_neutrals {
+        if let Some(orbit) = layout.place(&orbits, rng) {
+            orbits.push((orbit, config.start_ships.rand(rng)));
+        }
+    }
+
+    let mut planets = Vec::new();
+    for (num, (orbit, ship_count)) in orbits.into_iter().enumerate() {
+        for (player, (x, y)) in orbit.into_iter().enumerate() {
+            // the first orbit holds the start planets
+            let owner = if num == 0 {
+                Some(player as u64 + 1)
+            } else {
+                None
+            };
+            planets.push(Planet {
+                x: x,
+                y: y,
+                owner: owner,
+                ship_count: ship_count as u64,
+                name: format!("Planet_{}", planets.len()),
+                growth_rate: 1,
+                radius: None,
+            });
+        }
+    }
+
+    Ok(Map { planets: planets })
+}
+
+/// Describes where planets can go, and how they are copied for every player.
+struct Layout {
+    symmetry: Symmetry,
+    num_players: usize,
+    center: (f64, f64),
+    /// Half of the width and height of the map.
+    extent: (f64, f64),
+    min_spacing: f64,
+}
+
+impl Layout {
+    fn new(config: &Config, num_players: usize) -> Self {
+        let h = &config.horizontal_bound;
+        let v = &config.vertical_bound;
+        // the upper bounds are exclusive
+        let (left, right) = (h.min as f64, (h.max - 1) as f64);
+        let (bottom, top) = (v.min as f64, (v.max - 1) as f64);
+
+        let symmetry = match config.symmetry {
+            Some(Symmetry::Mirror) if num_players == 2 => Symmetry::Mirror,
+            _ => Symmetry::Rotational,
+        };
+
+        Layout {
+            symmetry: symmetry,
+            num_players: num_players,
+            center: ((left + right) / 2.0, (bottom + top) / 2.0),
+            extent: ((right - left) / 2.0, (top - bottom) / 2.0),
+            min_spacing: config.min_spacing,
+        }
+    }
+
+    /// Pick a random point in the part of the map that belongs to the first
+    /// player.
+    fn sample<R: Rng>(&self, rng: &mut R) -> (f64, f64) {
+        let (cx, cy) = self.center;
+        let (ex, ey) = self.extent;
+        match self.symmetry {
+            Symmetry::Rotational => {
+                // the largest circle that fits in the map
+                let radius = ex.min(ey) * rng.gen::<f64>().sqrt();
+                let angle = rng.gen::<f64>() * 2.0 * PI / self.num_players as f64;
+                (cx + radius * angle.cos(), cy + radius * angle.sin())
+            }
+            Symmetry::Mirror => {
+                (cx - ex * rng.gen::<f64>(), cy + ey * (2.0 * rng.gen::<f64>() - 1.0))
+            }
+        }
+    }
+
+    /// The copies of a point for every player, in player order.
+    fn images(&self, (x, y): (f64, f64)) -> Vec<(f64, f64)> {
+        let (cx, cy) = self.center;
+        match self.symmetry {
+            Symmetry::Rotational => (0..self.num_players).map(|player| {
+                let angle = 2.0 * PI * player as f64 / self.num_players as f64;
+                let (sin, cos) = angle.sin_cos();
+                let (dx, dy) = (x - cx, y - cy);
+                (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+            }).collect(),
+            Symmetry::Mirror => vec![(x, y), (2.0 * cx - x, y)],
+        }
+    }
+
+    /// Find a spot for a planet and its copies, keeping them at least
+    /// `min_spacing` away from each other and from the existing planets.
+    fn place<R: Rng>(&self, orbits: &[(Vec<(f64, f64)>, usize)], rng: &mut R)
+        -> Option<Vec<(f64, f64)>>
+    {
+        let taken: Vec<(f64, f64)> = orbits.iter()
+            .flat_map(|&(ref orbit, _)| orbit.iter().cloned())
+            .collect();
+
+        for _ in 0..PLACEMENT_ATTEMPTS {
+            let orbit = self.images(self.sample(rng));
+            let fits = orbit.iter().enumerate().all(|(i, &point)| {
+                orbit[i + 1..].iter().chain(taken.iter())
+                    .all(|&other| distance(point, other) >= self.min_spacing)
+            });
+            if fits {
+                return Some(orbit);
+            }
+        }
+        return None;
+    }
+}
+
+fn distance((x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
+    ((x1 - x2).powi(2) + (y1 - y2).powi(2)).sqrt()
+}
+
+
+#[cfg(test)]
+mod test {
+    use rand::{SeedableRng, Isaac64Rng};
+
+    use super::*;
+    use util::Bound;
+
+    fn config(players: usize, symmetry: Symmetry) -> Config {
+        let mut config = Config::new();
+        config.player_amount = Bound { min: players, max: players + 1 };
+        config.symmetry = Some(symmetry);
+        config
+    }
+
+    /// Checks that the map stays the same under given transformation.
+    fn assert_symmetric<F>(map: &Map, transform: F)
+        where F: Fn(f64, f64) -> (f64, f64)
+    {
+        for planet in map.planets.iter() {
+            let (x, y) = transform(planet.x, planet.y);
+            let image = map.planets.iter()
+                .find(|p| (p.x - x).abs() < 1e-3 && (p.y - y).abs() < 1e-3)
+                .expect("planet has no image");
+            assert_eq!(image.ship_count, planet.ship_count);
+            assert_eq!(image.owner.is_some(), planet.owner.is_some());
+        }
+    }
+
+    fn assert_spaced(map: &Map, min_spacing: f64) {
+        for (i, a) in map.planets.iter().enumerate() {
+            for b in map.planets[i + 1..].iter() {
+                assert!(distance((a.x, a.y), (b.x, b.y)) >= min_spacing);
+            }
+        }
+    }
+
+    #[test]
+    fn rotational_maps() {
+        for seed in 0..20 {
+            let mut rng = Isaac64Rng::from_seed(&[seed]);
+            let config = config(3, Symmetry::Rotational);
+            let map = create_symmetric_map(&config, &mut rng).unwrap();
+
+            let starts: Vec<_> = map.planets.iter()
+                .filter_map(|p| p.owner)
+                .collect();
+            assert_eq!(starts, vec![1, 2, 3]);
+            assert_spaced(&map, config.min_spacing);
+            // the center of the default bounds is the origin
+            let (sin, cos) = (2.0 * PI / 3.0).sin_cos();
+            assert_symmetric(&map, |x, y| (x * cos - y * sin, x * sin + y * cos));
+        }
+    }
+
+    #[test]
+    fn mirrored_maps() {
+        for seed in 0..20 {
+            let mut rng = Isaac64Rng::from_seed(&[seed]);
+            let config = config(2, Symmetry::Mirror);
+            let map = create_symmetric_map(&config, &mut rng).unwrap();
+
+            assert_eq!(map.num_seats(), 2);
+            assert_spaced(&map, config.min_spacing);
+            assert_symmetric(&map, |x, y| (-x, y));
+        }
+    }
+}
@@ -1,10 +1,12 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use rand::distributions::range::SampleRange;
 use num::Integer;
 
 use std::ops::Range;
 use std::cmp::PartialOrd;
 
+/// A range of values, excluding `max`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bound<T> {
     pub min: T,
     pub max: T,
@@ -14,13 +16,13 @@ pub struct Bound<T> {
 impl <T> Bound<T>
 where T: SampleRange, T: PartialOrd, T: Copy, T: Integer {
 
-    pub fn rand(&self) -> T {
-        thread_rng().gen_range::<T>(self.min, self.max)
+    pub fn rand<R: Rng>(&self, rng: &mut R) -> T {
+        rng.gen_range::<T>(self.min, self.max)
     }
 
     // TODO: Figure out how to make this an Iterator or smthg
-    pub fn sample(&self, amount: usize) -> Vec<T> {
-        (0..amount).map(|_| self.rand()).collect()
+    pub fn sample<R: Rng>(&self, rng: &mut R, amount: usize) -> Vec<T> {
+        (0..amount).map(|_| self.rand(rng)).collect()
     }
 
     pub fn range(&self) -> Range<T> {
@@ -1,17 +1,19 @@
 extern crate map_generator;
+extern crate rand;
 
 use std::fs::File;
 use std::io::prelude::*;
 
 use map_generator::Config;
 use map_generator::create_map;
+use rand::thread_rng;
 
 
 #[test]
 #[allow(unused_must_use)]
 fn test_create_map() {
     let config = Config::new();
-    let map = create_map(&config);
+    let map = create_map(&config, &mut thread_rng()).unwrap();
     match File::create("map.json") {
         Ok(mut file) => {
             file.write_all(format!("{}", map).as_bytes());
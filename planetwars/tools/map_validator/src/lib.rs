@@ -0,0 +1,11 @@
+//! Checks planet wars maps for mistakes, and reports how fair they are to
+//! every seat.
+
+extern crate map_model;
+
+mod symmetry;
+mod validator;
+
+pub use map_model::{Map, Planet};
+pub use symmetry::Symmetry;
+pub use validator::{validate, Report, SeatStats};
@@ -0,0 +1,56 @@
+extern crate map_validator;
+
+use std::env;
+use std::process;
+
+use map_validator::{validate, Map};
+
+const USAGE: &str = "\
+usage: map_validator [--players <n>] <map file>...
+
+Checks maps for errors, and reports how fair they are to every player.
+Exits with a non-zero status when any map has errors.
+
+  --players <n>  the number of players the maps are meant for";
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut num_players = None;
+
+    if args.first().map(|arg| arg.as_str()) == Some("--players") {
+        num_players = match args.get(1).and_then(|n| n.parse().ok()) {
+            Some(n) => Some(n),
+            None => usage("expected a number of players"),
+        };
+        args.drain(..2);
+    }
+    if args.is_empty() {
+        usage("expected at least one map file");
+    }
+
+    let mut valid = true;
+    for path in args.iter() {
+        println!("{}", path);
+        match Map::read(path) {
+            Ok(map) => {
+                let report = validate(&map, num_players);
+                valid &= report.is_valid();
+                print!("{}", report);
+            }
+            Err(err) => {
+                valid = false;
+                println!("error: could not read map: {}", err);
+            }
+        }
+        println!();
+    }
+
+    if !valid {
+        process::exit(1);
+    }
+}
+
+fn usage(msg: &str) -> ! {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
@@ -0,0 +1,125 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use map_model::Map;
+
+/// How far a transformed planet may be off from the planet it maps onto.
+const EPSILON: f64 = 1e-3;
+
+/// A symmetry that maps the map onto itself, and every seat onto the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    /// Rotating the map around its center by `360 / n` degrees, for a map
+    /// with `n` seats.
+    Rotational(u64),
+    /// Mirroring the map over the line between the two seats.
+    Mirror,
+    None,
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Symmetry::Rotational(n) => write!(f, "rotational ({}-fold)", n),
+            Symmetry::Mirror => write!(f, "mirror"),
+            Symmetry::None => write!(f, "none"),
+        }
+    }
+}
+
+pub fn detect(map: &Map) -> Symmetry {
+    let seats = map.num_seats();
+    if seats < 2 {
+        return Symmetry::None;
+    }
+
+    let num_planets = map.planets.len() as f64;
+    let cx = map.planets.iter().map(|p| p.x).sum::<f64>() / num_planets;
+    let cy = map.planets.iter().map(|p| p.y).sum::<f64>() / num_planets;
+    let (sin, cos) = (2.0 * PI / seats as f64).sin_cos();
+    let rotation = |x: f64, y: f64| {
+        let (dx, dy) = (x - cx, y - cy);
+        (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+    };
+    if is_symmetry(map, seats, rotation) {
+        return Symmetry::Rotational(seats);
+    }
+
+    if seats == 2 {
+        // A mirror that swaps the seats maps a start of seat 1 onto a start
+        // of seat 2, so it has to be their perpendicular bisector.
+        let start = match map.starts(1).first() {
+            Some(&start) => start,
+            None => return Symmetry::None,
+        };
+        for image in map.starts(2) {
+            let (mx, my) = ((start.x + image.x) / 2.0, (start.y + image.y) / 2.0);
+            let (nx, ny) = (image.x - start.x, image.y - start.y);
+            let norm = (nx.powi(2) + ny.powi(2)).sqrt();
+            if norm < EPSILON {
+                continue;
+            }
+            let (nx, ny) = (nx / norm, ny / norm);
+            let mirror = |x: f64, y: f64| {
+                let d = (x - mx) * nx + (y - my) * ny;
+                (x - 2.0 * d * nx, y - 2.0 * d * ny)
+            };
+            if is_symmetry(map, seats, mirror) {
+                return Symmetry::Mirror;
+            }
+        }
+    }
+
+    Symmetry::None
+}
+
+/// Whether given transformation maps every planet onto an identical planet,
+/// and cycles through all seats.
+fn is_symmetry<F>(map: &Map, seats: u64, transform: F) -> bool
+    where F: Fn(f64, f64) -> (f64, f64)
+{
+    let mut seat_map: Vec<Option<u64>> = vec![None; seats as usize + 1];
+
+    for planet in map.planets.iter() {
+        let (x, y) = transform(planet.x, planet.y);
+        let image = map.planets.iter().find(|other| {
+            (other.x - x).abs() < EPSILON && (other.y - y).abs() < EPSILON
+        });
+        let image = match image {
+            Some(image) => image,
+            None => return false,
+        };
+
+        if image.ship_count != planet.ship_count
+            || image.growth_rate != planet.growth_rate
+        {
+            return false;
+        }
+
+        match (planet.owner, image.owner) {
+            (None, None) => {}
+            (Some(seat), Some(image_seat)) => {
+                let mapped = &mut seat_map[seat as usize];
+                match *mapped {
+                    None => *mapped = Some(image_seat),
+                    Some(other) if other != image_seat => return false,
+                    Some(_) => {}
+                }
+            }
+            _ => return false,
+        }
+    }
+
+    // Following the seat mapping from seat 1 should visit every seat.
+    let mut seat = 1;
+    for visited in 1..(seats + 1) {
+        seat = match seat_map.get(seat as usize).cloned().and_then(|s| s) {
+            Some(next) => next,
+            None => return false,
+        };
+        if seat == 1 {
+            return visited == seats;
+        }
+    }
+    false
+}
@@ -0,0 +1,225 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use map_model::{Map, Planet};
+use symmetry::{self, Symmetry};
+
+/// Seats whose statistics differ by more than this fraction are considered
+/// unfair.
+const TOLERANCE: f64 = 0.1;
+
+/// The outcome of validating a map.
+pub struct Report {
+    /// Problems that make the map unfit for play.
+    pub errors: Vec<String>,
+    /// Properties of the map that are probably unintended.
+    pub warnings: Vec<String>,
+    pub symmetry: Symmetry,
+    pub seats: Vec<SeatStats>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Statistics on what a seat starts with, and what it can easily take.
+pub struct SeatStats {
+    pub seat: u64,
+    pub start_planets: usize,
+    pub start_ships: u64,
+    pub start_growth: u64,
+    /// Distance from the seat's start planets to the nearest neutral planet.
+    pub nearest_neutral: Option<f64>,
+    /// The number of neutral planets that are closer to this seat than to
+    /// any other seat.
+    pub nearby_planets: usize,
+    /// The total garrison of those neutral planets.
+    pub nearby_ships: u64,
+    /// The total growth rate of those neutral planets.
+    pub nearby_growth: u64,
+}
+
+/// Validate a map. When the number of players is given, start planets for
+/// more players are reported as errors.
+pub fn validate(map: &Map, num_players: Option<u64>) -> Report {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    if map.planets.is_empty() {
+        errors.push("map has no planets".to_string());
+    }
+
+    let mut names = HashSet::new();
+    for planet in map.planets.iter() {
+        if !names.insert(&planet.name) {
+            errors.push(format!("planet name {} is used twice", planet.name));
+        }
+        if !planet.x.is_finite() || !planet.y.is_finite() {
+            errors.push(format!("planet {} has invalid coordinates", planet.name));
+        }
+        match (planet.owner, num_players) {
+            (Some(0), _) => errors.push(format!(
+                "planet {} is owned by player 0; players are numbered from 1",
+                planet.name,
+            )),
+            (Some(owner), Some(num_players)) if owner > num_players => {
+                errors.push(format!(
+                    "planet {} is owned by player {}, but the map is for {} players",
+                    planet.name, owner, num_players,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    for (i, planet) in map.planets.iter().enumerate() {
+        for other in map.planets[i + 1..].iter() {
+            if planet.x == other.x && planet.y == other.y {
+                errors.push(format!(
+                    "planets {} and {} are at the same coordinates",
+                    planet.name, other.name,
+                ));
+            }
+        }
+    }
+
+    let num_seats = num_players.unwrap_or_else(|| map.num_seats());
+    if num_seats < 2 {
+        errors.push("map has start planets for fewer than 2 players".to_string());
+    }
+    for seat in 1..(num_seats + 1) {
+        if map.starts(seat).is_empty() {
+            errors.push(format!("player {} has no start planet", seat));
+        }
+    }
+
+    let seats: Vec<SeatStats> = (1..(num_seats + 1))
+        .map(|seat| seat_stats(map, seat, num_seats))
+        .collect();
+    check_fairness(&seats, &mut warnings);
+
+    let symmetry = symmetry::detect(map);
+    if symmetry == Symmetry::None && num_seats >= 2 {
+        warnings.push("map is not symmetric".to_string());
+    }
+
+    Report {
+        errors,
+        warnings,
+        symmetry,
+        seats,
+    }
+}
+
+/// The distance from the start planets of a seat to given planet.
+fn seat_distance(map: &Map, seat: u64, planet: &Planet) -> f64 {
+    map.starts(seat).iter()
+        .map(|start| start.distance(planet))
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn seat_stats(map: &Map, seat: u64, num_seats: u64) -> SeatStats {
+    let starts = map.starts(seat);
+    let neutrals: Vec<&Planet> = map.planets.iter()
+        .filter(|planet| planet.owner.is_none())
+        .collect();
+
+    let nearest_neutral = neutrals.iter()
+        .map(|planet| seat_distance(map, seat, planet))
+        .filter(|distance| distance.is_finite())
+        .fold(None, |min: Option<f64>, d| Some(min.map_or(d, |m| m.min(d))));
+
+    let nearby: Vec<&&Planet> = neutrals.iter()
+        .filter(|planet| {
+            let distance = seat_distance(map, seat, planet);
+            (1..(num_seats + 1))
+                .filter(|&other| other != seat)
+                .all(|other| distance < seat_distance(map, other, planet))
+        })
+        .collect();
+
+    SeatStats {
+        seat,
+        start_planets: starts.len(),
+        start_ships: starts.iter().map(|p| p.ship_count).sum(),
+        start_growth: starts.iter().map(|p| p.growth_rate).sum(),
+        nearest_neutral,
+        nearby_planets: nearby.len(),
+        nearby_ships: nearby.iter().map(|p| p.ship_count).sum(),
+        nearby_growth: nearby.iter().map(|p| p.growth_rate).sum(),
+    }
+}
+
+/// Extracts a statistic from a seat, if it has a value.
+type Stat = fn(&SeatStats) -> Option<f64>;
+
+fn check_fairness(seats: &[SeatStats], warnings: &mut Vec<String>) {
+    let stats: [(&str, Stat); 5] = [
+        ("start ships", |s| Some(s.start_ships as f64)),
+        ("start growth", |s| Some(s.start_growth as f64)),
+        ("distance to the nearest neutral", |s| s.nearest_neutral),
+        ("nearby neutral ships", |s| Some(s.nearby_ships as f64)),
+        ("nearby neutral growth", |s| Some(s.nearby_growth as f64)),
+    ];
+
+    for &(name, stat) in stats.iter() {
+        let mut low: Option<(u64, f64)> = None;
+        let mut high: Option<(u64, f64)> = None;
+        for seat in seats.iter() {
+            let value = match stat(seat) {
+                Some(value) => value,
+                None => continue,
+            };
+            match low {
+                Some((_, low)) if low <= value => {}
+                _ => low = Some((seat.seat, value)),
+            }
+            match high {
+                Some((_, high)) if high >= value => {}
+                _ => high = Some((seat.seat, value)),
+            }
+        }
+
+        if let (Some((low_seat, low)), Some((high_seat, high))) = (low, high) {
+            if high - low > low * TOLERANCE {
+                warnings.push(format!(
+                    "map favours some players: {} is {} for player {}, but {} for player {}",
+                    name, round(low), low_seat, round(high), high_seat,
+                ));
+            }
+        }
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "symmetry: {}", self.symmetry)?;
+        for seat in self.seats.iter() {
+            write!(f,
+                "player {}: {} start planets with {} ships (growth {}), ",
+                seat.seat, seat.start_planets, seat.start_ships, seat.start_growth,
+            )?;
+            match seat.nearest_neutral {
+                Some(distance) => write!(f, "nearest neutral at {}, ", round(distance))?,
+                None => write!(f, "no neutrals, ")?,
+            }
+            writeln!(f,
+                "{} nearby neutrals with {} ships (growth {})",
+                seat.nearby_planets, seat.nearby_ships, seat.nearby_growth,
+            )?;
+        }
+        for warning in self.warnings.iter() {
+            writeln!(f, "warning: {}", warning)?;
+        }
+        for error in self.errors.iter() {
+            writeln!(f, "error: {}", error)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+extern crate map_validator;
+extern crate serde_json;
+
+use std::fs;
+
+use map_validator::{validate, Map, Symmetry};
+
+fn map(planets: &str) -> Map {
+    serde_json::from_str(&format!(r#"{{ "planets": {} }}"#, planets)).unwrap()
+}
+
+#[test]
+fn reports_errors() {
+    let map = map(r#"[
+        { "name": "protos", "x": 0, "y": 0, "owner": 1, "ship_count": 6 },
+        { "name": "protos", "x": 5, "y": 0, "owner": 3, "ship_count": 6 },
+        { "name": "tritos", "x": 5, "y": 0, "ship_count": 6 }
+    ]"#);
+    let report = validate(&map, Some(2));
+    assert_eq!(report.errors, vec![
+        "planet name protos is used twice",
+        "planet protos is owned by player 3, but the map is for 2 players",
+        "planets protos and tritos are at the same coordinates",
+        "player 2 has no start planet",
+    ]);
+}
+
+#[test]
+fn detects_rotational_symmetry() {
+    let map = map(r#"[
+        { "name": "a", "x": 2, "y": 0, "owner": 1, "ship_count": 5 },
+        { "name": "b", "x": 0, "y": 2, "owner": 2, "ship_count": 5 },
+        { "name": "c", "x": -2, "y": 0, "owner": 3, "ship_count": 5 },
+        { "name": "d", "x": 0, "y": -2, "owner": 4, "ship_count": 5 },
+        { "name": "e", "x": 0, "y": 0, "ship_count": 20, "growth_rate": 4 }
+    ]"#);
+    let report = validate(&map, None);
+    assert_eq!(report.symmetry, Symmetry::Rotational(4));
+    assert!(report.errors.is_empty());
+    assert!(report.warnings.is_empty());
+}
+
+#[test]
+fn detects_mirror_symmetry() {
+    // not point symmetric, but mirrored over the y axis
+    let map = map(r#"[
+        { "name": "a", "x": -4, "y": 0, "owner": 1, "ship_count": 5 },
+        { "name": "b", "x": 4, "y": 0, "owner": 2, "ship_count": 5 },
+        { "name": "c", "x": -2, "y": 3, "ship_count": 8 },
+        { "name": "d", "x": 2, "y": 3, "ship_count": 8 }
+    ]"#);
+    let report = validate(&map, None);
+    assert_eq!(report.symmetry, Symmetry::Mirror);
+    assert!(report.warnings.is_empty());
+}
+
+#[test]
+fn warns_about_unfair_maps() {
+    let map = map(r#"[
+        { "name": "a", "x": -4, "y": 0, "owner": 1, "ship_count": 5 },
+        { "name": "b", "x": 4, "y": 0, "owner": 2, "ship_count": 5 },
+        { "name": "c", "x": 3, "y": 1, "ship_count": 8 }
+    ]"#);
+    let report = validate(&map, None);
+    assert_eq!(report.symmetry, Symmetry::None);
+    assert_eq!(report.seats[1].nearby_planets, 1);
+    assert!(report.warnings.iter().any(|w| w.contains("nearest neutral")));
+    assert!(report.warnings.iter().any(|w| w == "map is not symmetric"));
+}
+
+#[test]
+fn repository_maps_are_valid() {
+    for entry in fs::read_dir("../../maps").unwrap() {
+        let path = entry.unwrap().path();
+        let report = validate(&Map::read(&path).unwrap(), None);
+        assert!(report.is_valid(), "{}: {:?}", path.display(), report.errors);
+    }
+}
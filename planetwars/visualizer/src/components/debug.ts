@@ -0,0 +1,40 @@
+import Game from "./game";
+import { Component } from "react";
+
+import * as h from 'react-hyperscript';
+import * as hh from 'hyperscript-helpers';
+const { div, ul, li, span } = (hh as any)(h);
+
+import * as styles from './debug.scss';
+
+interface DebugViewProps {
+  game: Game;
+  turnNum: number;
+  playerNum: number;
+}
+
+// Shows the debug output one player sent along in the current turn.
+export default class DebugView extends Component<DebugViewProps> {
+  public render() {
+    const { game, turnNum, playerNum } = this.props;
+    const debug = game.matchLog.playerDebug(playerNum)[turnNum];
+    const messages = (debug && debug.messages) || [];
+    const annotations = (debug && debug.annotations) || [];
+
+    const items = messages.map((message) => li(message))
+      .concat(annotations.map((annotation) => {
+        return li([
+          span(`.${styles.planet}`, annotation.planet),
+          ` ${annotation.label}`,
+        ]);
+      }));
+
+    return div(`.${styles.debug}`, [
+      div({ style: { color: game.playerColor(playerNum) } }, [
+        h('i.fa.fa-bug', { 'aria-hidden': true }),
+        ` ${game.playerName(playerNum)}`,
+      ]),
+      ul(items),
+    ]);
+  }
+}
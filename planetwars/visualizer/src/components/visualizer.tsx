@@ -5,6 +5,7 @@ import { MatchLog } from 'planetwars-match-log';
 import Game from './game';
 import Scoreboard from './scoreboard';
 import Controls from './controls';
+import DebugView from './debug';
 
 import { Renderer } from './renderer';
 
@@ -15,6 +16,9 @@ interface VisualizerProps {
   playerName: (playerNum: number) => string;
   matchLog: MatchLog;
   assetPrefix?: string;
+  // Show the debug output of this player. Debug output is meant for the
+  // bot's owner only, so none is shown by default.
+  debugPlayer?: number;
 }
 
 interface VisualizerState {
@@ -98,10 +102,22 @@ export class Visualizer extends React.Component<VisualizerProps, VisualizerState
       />
     );
 
+    let debugView;
+    if (this.props.debugPlayer) {
+      debugView = (
+        <DebugView
+          game={this.game}
+          turnNum={this.state.turnNum}
+          playerNum={this.props.debugPlayer}
+        />
+      );
+    }
+
     return (
       <div className={styles.visualizer}>
         {controls}
         {scoreboard}
+        {debugView}
         {renderer}
       </div>
     );